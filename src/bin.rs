@@ -0,0 +1,304 @@
+//! Access to the data an XDF describes, in a bin file read from an ECU.
+//! Addresses in the XDF are offset by the header `baseoffset` to find data in the file,
+//! and must lie within the header REGION when one is declared.
+//!
+//! Element size, byte order and signedness come from an item's `EmbeddedData`,
+//! falling back to the header DEFAULTS when `mmedtypeflags` or `mmedelementsizebits` are not given.
+//! Flags replace the defaults as a whole: an item with any `mmedtypeflags`, even `0x0`,
+//! ignores the DEFAULTS `signed`, `lsbfirst` and `float` settings.
+//! Strides and column-major storage are laid out as described on `EmbeddedData`, values are always handled row by row.
+//! Values are handled as `f64`, which holds every 8, 16 and 32 bit integer exactly.
+//!
+//! Elements of 32 or 64 bits can hold IEEE-754 floats instead of integers,
+//! when `mmedtypeflags` has `TYPE_FLOAT` set or, for items without flags, the header DEFAULTS have `float` set.
+//! The signed flag does not apply to floats, the byte order flag does.
+
+use std::{fmt, fs, io, ops::Range, path::Path};
+
+use crate::{data_types::*, math::MathError};
+
+/// `mmedtypeflags` bit set for signed elements.
+pub const TYPE_SIGNED: u32 = 0x01;
+/// `mmedtypeflags` bit set when the least significant byte is stored first.
+pub const TYPE_LSB_FIRST: u32 = 0x02;
+/// `mmedtypeflags` bit set when data is stored column by column, see `EmbeddedData`.
+pub const TYPE_COLUMN_MAJOR: u32 = 0x04;
+/// `mmedtypeflags` bit set for IEEE-754 floating point elements.
+pub const TYPE_FLOAT: u32 = 0x10000;
+
+/// Reason data could not be read from or written to a bin, see `BinImage`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinError {
+    /// Item has no data address
+    MissingAddress,
+    /// Element size is not supported: integers are 8, 16 or 32 bits, floats 32 or 64. Contains the rejected size in bits
+    ElementSize(u32),
+    /// Element lies outside the REGION declared in the header, contains its address
+    OutsideRegion(u64),
+    /// Element lies past the end of the bin, contains its offset in the file
+    OutOfBounds(u64),
+    /// Number of values written does not match the number of elements
+    Count { expected: usize, found: usize },
+    /// Value cannot be stored in the element
+    OutOfRange { value: f64, min: f64, max: f64 },
+    /// Stride is not a whole number of bytes, contains the stride in bits
+    Stride(i32),
+    /// Conversion between stored and displayed values failed
+    Math(MathError),
+}
+
+impl fmt::Display for BinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingAddress => write!(f, "missing data address"),
+            Self::ElementSize(bits) => write!(f, "unsupported element size of {bits} bits"),
+            Self::OutsideRegion(address) => {
+                write!(f, "address 0x{address:X} is outside the region")
+            }
+            Self::OutOfBounds(offset) => {
+                write!(f, "offset 0x{offset:X} is past the end of the bin")
+            }
+            Self::Count { expected, found } => {
+                write!(f, "expected {expected} values, found {found}")
+            }
+            Self::OutOfRange { value, min, max } => {
+                write!(f, "{value} is outside the range {min} to {max}")
+            }
+            Self::Stride(bits) => write!(f, "stride of {bits} bits is not a whole number of bytes"),
+            Self::Math(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for BinError {}
+
+impl From<MathError> for BinError {
+    fn from(e: MathError) -> Self {
+        Self::Math(e)
+    }
+}
+
+/// Contents of a bin file, along with the header settings used to find data in it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BinImage {
+    data: Vec<u8>,
+    base_offset: u32,
+    /// Addresses data may be read from, any address when `None`
+    region: Option<Range<u64>>,
+    defaults: Defaults,
+}
+
+/// Storage format of a single element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Element {
+    bytes: usize,
+    signed: bool,
+    lsb_first: bool,
+    float: bool,
+}
+
+impl Element {
+    fn decode(&self, raw: &[u8]) -> f64 {
+        let mut value: u64 = 0;
+        for i in 0..self.bytes {
+            let byte = if self.lsb_first {
+                raw[self.bytes - 1 - i]
+            } else {
+                raw[i]
+            };
+            value = value << 8 | u64::from(byte);
+        }
+        let bits = self.bytes * 8;
+        if self.float {
+            match self.bytes {
+                4 => f64::from(f32::from_bits(value as u32)),
+                _ => f64::from_bits(value),
+            }
+        } else if self.signed && value >> (bits - 1) & 1 == 1 {
+            (value as i64 - (1 << bits)) as f64
+        } else {
+            value as f64
+        }
+    }
+
+    /// Smallest and largest value the element can hold.
+    pub(crate) fn range(&self) -> (f64, f64) {
+        let bits = self.bytes as u32 * 8;
+        if self.float {
+            match self.bytes {
+                4 => (-f64::from(f32::MAX), f64::from(f32::MAX)),
+                _ => (f64::MIN, f64::MAX),
+            }
+        } else if self.signed {
+            (
+                -(2f64.powi(bits as i32 - 1)),
+                2f64.powi(bits as i32 - 1) - 1.0,
+            )
+        } else {
+            (0.0, 2f64.powi(bits as i32) - 1.0)
+        }
+    }
+
+    /// Value the element can hold that is closest to `value`, ignoring its range.
+    pub(crate) fn nearest(&self, value: f64) -> f64 {
+        match (self.float, self.bytes) {
+            (true, 4) => f64::from(value as f32),
+            (true, _) => value,
+            (false, _) => value.round(),
+        }
+    }
+
+    fn encode(&self, value: f64, raw: &mut [u8]) -> Result<(), BinError> {
+        let (min, max) = self.range();
+        if !(min..=max).contains(&value) || (!self.float && value.fract() != 0.0) {
+            return Err(BinError::OutOfRange { value, min, max });
+        }
+        let mut value = match (self.float, self.bytes) {
+            (true, 4) => u64::from((value as f32).to_bits()),
+            (true, _) => value.to_bits(),
+            (false, _) => value as i64 as u64,
+        };
+        for i in 0..self.bytes {
+            let at = if self.lsb_first {
+                i
+            } else {
+                self.bytes - 1 - i
+            };
+            raw[at] = value as u8;
+            value >>= 8;
+        }
+        Ok(())
+    }
+}
+
+impl BinImage {
+    /// Bin without header settings: no base offset or region, unsigned big endian bytes unless items say otherwise.
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(fs::read(path)?))
+    }
+
+    /// Uses the base offset, region and defaults of a definition's header.
+    pub fn with_header(mut self, header: &XDFHeader) -> Self {
+        self.base_offset = header.baseoffset.unwrap_or(0);
+        self.region = header.region.as_ref().and_then(|r| {
+            let start = u64::from(r.startaddress?);
+            Some(start..start + u64::from(r.size?))
+        });
+        self.defaults = header.defaults.clone().unwrap_or_default();
+        self
+    }
+
+    /// Uses the header of a definition if it has one, see `with_header`.
+    pub fn with_format(self, format: &XDFFormat) -> Self {
+        match &format.header {
+            Some(header) => self.with_header(header),
+            None => self,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Storage format of the elements described by `data`.
+    /// `mmedtypeflags` takes precedence over all of the DEFAULTS flags, they are only used when it is missing.
+    pub(crate) fn element(&self, data: &EmbeddedData) -> Result<Element, BinError> {
+        let bits = data
+            .mmedelementsizebits
+            .or(self.defaults.datasizeinbits)
+            .unwrap_or(8);
+        let flags = data.mmedtypeflags.unwrap_or_else(|| {
+            let flag = |value: Option<u32>, bit: u32| if value.unwrap_or(0) != 0 { bit } else { 0 };
+            flag(self.defaults.signed, TYPE_SIGNED)
+                | flag(self.defaults.lsbfirst, TYPE_LSB_FIRST)
+                | flag(self.defaults.float, TYPE_FLOAT)
+        });
+        let float = flags & TYPE_FLOAT != 0;
+        let valid = if float {
+            matches!(bits, 32 | 64)
+        } else {
+            matches!(bits, 8 | 16 | 32)
+        };
+        if !valid {
+            return Err(BinError::ElementSize(bits));
+        }
+        Ok(Element {
+            bytes: bits as usize / 8,
+            signed: flags & TYPE_SIGNED != 0,
+            lsb_first: flags & TYPE_LSB_FIRST != 0,
+            float,
+        })
+    }
+
+    /// File offsets of the elements described by `data`, row by row.
+    fn offsets(&self, data: &EmbeddedData, element: Element) -> Result<Vec<usize>, BinError> {
+        let address = u64::from(data.mmedaddress.ok_or(BinError::MissingAddress)?);
+        let size = element.bytes as u64;
+        data.element_offsets(size)?
+            .into_iter()
+            .map(|relative| {
+                let start = address + relative;
+                if let Some(region) = &self.region {
+                    if start < region.start || start + size > region.end {
+                        return Err(BinError::OutsideRegion(start));
+                    }
+                }
+                let offset = start + u64::from(self.base_offset);
+                if offset + size > self.data.len() as u64 {
+                    return Err(BinError::OutOfBounds(offset));
+                }
+                Ok(offset as usize)
+            })
+            .collect()
+    }
+
+    /// Raw values of the elements described by `data`, row by row.
+    pub fn read(&self, data: &EmbeddedData) -> Result<Vec<f64>, BinError> {
+        let element = self.element(data)?;
+        let offsets = self.offsets(data, element)?;
+        Ok(offsets
+            .into_iter()
+            .map(|offset| element.decode(&self.data[offset..offset + element.bytes]))
+            .collect())
+    }
+
+    /// Stores raw values in the elements described by `data`, row by row.
+    /// Values must fit the element, and be whole numbers unless it holds floats. Nothing is written if any of them does not.
+    pub fn write(&mut self, data: &EmbeddedData, values: &[f64]) -> Result<(), BinError> {
+        let element = self.element(data)?;
+        let offsets = self.offsets(data, element)?;
+        if offsets.len() != values.len() {
+            return Err(BinError::Count {
+                expected: offsets.len(),
+                found: values.len(),
+            });
+        }
+        let mut raw = vec![0; element.bytes];
+        for value in values {
+            element.encode(*value, &mut raw)?;
+        }
+        for (offset, value) in offsets.into_iter().zip(values) {
+            element.encode(*value, &mut self.data[offset..offset + element.bytes])?;
+        }
+        Ok(())
+    }
+}
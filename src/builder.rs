@@ -0,0 +1,589 @@
+//! Builders for creating tables and constants in code.
+//! They fill in the fields TunerPro expects (axis ids, strides, DALINK, datatype, ...) the way TunerPro writes them,
+//! and check the configuration for consistency before returning the item.
+//!
+//! Tables get the usual three axes: `x` for columns, `y` for rows and `z` for the data itself.
+//! Unless configured otherwise the x and y axes are label axes numbering the columns and rows.
+
+use std::fmt;
+
+use crate::{data_types::*, math::MathError};
+
+/// Reason a builder rejected its configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// A table has no rows or no columns
+    EmptyTable,
+    /// Element size is not 8, 16 or 32 bits, contains the rejected size
+    ElementSize(u32),
+    /// Number of labels of an axis does not match the table dimension it describes
+    LabelCount {
+        axis: String,
+        expected: u32,
+        found: usize,
+    },
+    /// Item has no data address
+    MissingAddress,
+    /// Data would extend past the end of the 32 bit address space
+    AddressOverflow,
+    /// Conversion equation is empty
+    EmptyEquation,
+    /// Conversion equation cannot be evaluated, see `math`
+    Equation(MathError),
+    /// Axis is linked to the table it belongs to, contains the uniqueid of the table
+    SelfLink(u32),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyTable => write!(f, "table must have at least one row and column"),
+            Self::ElementSize(bits) => {
+                write!(f, "element size must be 8, 16 or 32 bits, not {bits}")
+            }
+            Self::LabelCount {
+                axis,
+                expected,
+                found,
+            } => write!(f, "axis {axis} needs {expected} labels, found {found}"),
+            Self::MissingAddress => write!(f, "missing data address"),
+            Self::AddressOverflow => write!(f, "data extends past the end of the address space"),
+            Self::EmptyEquation => write!(f, "equation is empty"),
+            Self::Equation(e) => write!(f, "invalid equation: {e}"),
+            Self::SelfLink(uid) => write!(f, "axis links to its own table 0x{uid:X}"),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl From<MathError> for BuildError {
+    fn from(e: MathError) -> Self {
+        Self::Equation(e)
+    }
+}
+
+/// Source of the values of a table's x or y axis.
+#[derive(Debug, Clone, PartialEq)]
+enum AxisValues {
+    /// Fixed values stored in the XDF
+    Labels(Vec<String>),
+    /// Values stored in the bin
+    Embedded { address: u32, element_bits: u32 },
+    /// Values taken from the table with the given uniqueid
+    Linked(u32),
+}
+
+/// X or y axis of a table built by `TableBuilder`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AxisBuilder {
+    values: AxisValues,
+    units: Option<String>,
+    equation: String,
+    decimal_places: Option<u32>,
+    type_flags: Option<u32>,
+}
+
+impl AxisBuilder {
+    fn new(values: AxisValues) -> Self {
+        Self {
+            values,
+            units: None,
+            equation: "X".into(),
+            decimal_places: None,
+            type_flags: None,
+        }
+    }
+
+    /// Axis with fixed values, stored in the XDF as LABEL elements. There must be one label per row or column.
+    pub fn labels<S: ToString>(labels: impl IntoIterator<Item = S>) -> Self {
+        Self::new(AxisValues::Labels(
+            labels.into_iter().map(|l| l.to_string()).collect(),
+        ))
+    }
+
+    /// Axis with its values stored in the bin, one element per row or column starting at `address`.
+    pub fn embedded(address: u32, element_bits: u32) -> Self {
+        Self::new(AxisValues::Embedded {
+            address,
+            element_bits,
+        })
+    }
+
+    /// Axis that takes its values from another table, e.g. TVUB takes its axis from TVUB_AXIS.
+    pub fn linked(uid: u32) -> Self {
+        Self::new(AxisValues::Linked(uid))
+    }
+
+    pub fn units(mut self, units: impl Into<String>) -> Self {
+        self.units = Some(units.into());
+        self
+    }
+
+    /// Conversion from the stored value, `X` by default.
+    pub fn equation(mut self, equation: impl Into<String>) -> Self {
+        self.equation = equation.into();
+        self
+    }
+
+    pub fn decimal_places(mut self, places: u32) -> Self {
+        self.decimal_places = Some(places);
+        self
+    }
+
+    /// `mmedtypeflags` of embedded axis data.
+    pub fn type_flags(mut self, flags: u32) -> Self {
+        self.type_flags = Some(flags);
+        self
+    }
+
+    fn label_count(&self) -> Option<u32> {
+        match &self.values {
+            AxisValues::Labels(labels) => labels.len().try_into().ok(),
+            _ => None,
+        }
+    }
+
+    /// Builds the axis `id` with `count` values, `element_bits` is used for label and linked axes.
+    fn build(
+        &self,
+        id: &str,
+        count: u32,
+        table: Option<u32>,
+        element_bits: u32,
+    ) -> Result<XDFAxis, BuildError> {
+        check_equation(&self.equation)?;
+        // Label and linked axes keep an EMBEDDEDDATA without an address, as TunerPro does
+        let unstored = EmbeddedData {
+            mmedelementsizebits: Some(element_bits),
+            mmedmajorstridebits: Some(-32),
+            mmedminorstridebits: Some(0),
+            ..Default::default()
+        };
+        let mut axis = XDFAxis {
+            id: Some(id.into()),
+            uid: Some(0),
+            count: Some(count),
+            datatype: Some(0),
+            unittype: Some(0),
+            dalink_index: Some(0),
+            unit: self.units.clone(),
+            decimalplaces: self.decimal_places,
+            math: Some(math(&self.equation)),
+            ..Default::default()
+        };
+        match &self.values {
+            AxisValues::Labels(labels) => {
+                if labels.len() != count as usize {
+                    return Err(BuildError::LabelCount {
+                        axis: id.into(),
+                        expected: count,
+                        found: labels.len(),
+                    });
+                }
+                axis.embeddeddata = Some(unstored);
+                axis.labels = labels
+                    .iter()
+                    .zip(0..)
+                    .map(|(value, index)| Label {
+                        index: Some(index),
+                        value: Some(value.clone()),
+                    })
+                    .collect();
+            }
+            &AxisValues::Embedded {
+                address,
+                element_bits,
+            } => {
+                check_data(address, count, element_bits)?;
+                axis.embeddeddata = Some(EmbeddedData {
+                    mmedtypeflags: self.type_flags,
+                    mmedaddress: Some(address),
+                    mmedelementsizebits: Some(element_bits),
+                    // TunerPro counts the breakpoints of both x and y axes as columns
+                    mmedcolcount: Some(count),
+                    mmedmajorstridebits: Some(0),
+                    mmedminorstridebits: Some(0),
+                    ..Default::default()
+                });
+                axis.embedinfo = Some(EmbedInfo {
+                    etype: Some(1),
+                    linkobjid: None,
+                });
+            }
+            &AxisValues::Linked(uid) => {
+                if table == Some(uid) {
+                    return Err(BuildError::SelfLink(uid));
+                }
+                axis.embeddeddata = Some(unstored);
+                axis.embedinfo = Some(EmbedInfo {
+                    etype: Some(3),
+                    linkobjid: Some(uid),
+                });
+            }
+        }
+        Ok(axis)
+    }
+}
+
+/// Builds an `XDFTable`, e.g. `TableBuilder::new("KFMIRL").rows(16).cols(12).address(0x1EF9E).element_bits(16).equation("X*0.023438").build()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableBuilder {
+    title: String,
+    description: Option<String>,
+    uid: Option<u32>,
+    flags: u32,
+    categories: Vec<u32>,
+    rows: Option<u32>,
+    cols: Option<u32>,
+    address: Option<u32>,
+    element_bits: u32,
+    type_flags: Option<u32>,
+    equation: String,
+    units: Option<String>,
+    decimal_places: Option<u32>,
+    min: Option<f32>,
+    max: Option<f32>,
+    output_type: Option<u32>,
+    x: Option<AxisBuilder>,
+    y: Option<AxisBuilder>,
+}
+
+impl TableBuilder {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            description: None,
+            uid: None,
+            flags: 0,
+            categories: Vec::new(),
+            rows: None,
+            cols: None,
+            address: None,
+            element_bits: 8,
+            type_flags: None,
+            equation: "X".into(),
+            units: None,
+            decimal_places: None,
+            min: None,
+            max: None,
+            output_type: None,
+            x: None,
+            y: None,
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn uid(mut self, uid: u32) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    /// `flags` attribute of the XDFTABLE element, 0 by default.
+    pub fn flags(mut self, flags: u32) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Adds the table to a category, may be called more than once.
+    pub fn category(mut self, category: u32) -> Self {
+        self.categories.push(category);
+        self
+    }
+
+    /// Number of rows, defaults to the number of y axis labels or 1.
+    pub fn rows(mut self, rows: u32) -> Self {
+        self.rows = Some(rows);
+        self
+    }
+
+    /// Number of columns, defaults to the number of x axis labels or 1.
+    pub fn cols(mut self, cols: u32) -> Self {
+        self.cols = Some(cols);
+        self
+    }
+
+    /// Address of the table data.
+    pub fn address(mut self, address: u32) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// Size of each data element, 8 by default.
+    pub fn element_bits(mut self, bits: u32) -> Self {
+        self.element_bits = bits;
+        self
+    }
+
+    /// `mmedtypeflags` of the table data.
+    pub fn type_flags(mut self, flags: u32) -> Self {
+        self.type_flags = Some(flags);
+        self
+    }
+
+    /// Conversion from the stored value, `X` by default.
+    pub fn equation(mut self, equation: impl Into<String>) -> Self {
+        self.equation = equation.into();
+        self
+    }
+
+    pub fn units(mut self, units: impl Into<String>) -> Self {
+        self.units = Some(units.into());
+        self
+    }
+
+    pub fn decimal_places(mut self, places: u32) -> Self {
+        self.decimal_places = Some(places);
+        self
+    }
+
+    pub fn min(mut self, min: f32) -> Self {
+        self.min = Some(min);
+        self
+    }
+
+    pub fn max(mut self, max: f32) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    pub fn output_type(mut self, output_type: u32) -> Self {
+        self.output_type = Some(output_type);
+        self
+    }
+
+    /// Column axis, a label axis numbering the columns by default.
+    pub fn x_axis(mut self, axis: AxisBuilder) -> Self {
+        self.x = Some(axis);
+        self
+    }
+
+    /// Row axis, a label axis numbering the rows by default.
+    pub fn y_axis(mut self, axis: AxisBuilder) -> Self {
+        self.y = Some(axis);
+        self
+    }
+
+    /// Checks the configuration and builds the table.
+    pub fn build(&self) -> Result<XDFTable, BuildError> {
+        let dimension = |set: Option<u32>, axis: &Option<AxisBuilder>| {
+            set.or_else(|| axis.as_ref()?.label_count()).unwrap_or(1)
+        };
+        let (rows, cols) = (dimension(self.rows, &self.y), dimension(self.cols, &self.x));
+        if rows == 0 || cols == 0 {
+            return Err(BuildError::EmptyTable);
+        }
+        let address = self.address.ok_or(BuildError::MissingAddress)?;
+        check_data(
+            address,
+            rows.checked_mul(cols).ok_or(BuildError::AddressOverflow)?,
+            self.element_bits,
+        )?;
+        check_equation(&self.equation)?;
+
+        let axis = |id, count, axis: &Option<AxisBuilder>| match axis {
+            Some(axis) => axis.build(id, count, self.uid, self.element_bits),
+            None => AxisBuilder::labels(0..count).build(id, count, self.uid, self.element_bits),
+        };
+        let x = axis("x", cols, &self.x)?;
+        let y = axis("y", rows, &self.y)?;
+        let z = XDFAxis {
+            id: Some("z".into()),
+            embeddeddata: Some(EmbeddedData {
+                mmedtypeflags: self.type_flags,
+                mmedaddress: Some(address),
+                mmedelementsizebits: Some(self.element_bits),
+                mmedrowcount: Some(rows),
+                mmedcolcount: (cols > 1).then_some(cols),
+                mmedmajorstridebits: Some(0),
+                mmedminorstridebits: Some(0),
+                ..Default::default()
+            }),
+            unit: self.units.clone(),
+            decimalplaces: self.decimal_places,
+            min: self.min,
+            max: self.max,
+            outputtype: self.output_type,
+            math: Some(math(&self.equation)),
+            ..Default::default()
+        };
+
+        Ok(XDFTable {
+            title: Some(self.title.clone()),
+            uid: self.uid,
+            flags: Some(self.flags),
+            catmem: category_memberships(&self.categories),
+            description: self.description.clone(),
+            axis: vec![x, y, z],
+            ..Default::default()
+        })
+    }
+}
+
+/// Builds an `XDFConstant`, e.g. `ConstantBuilder::new("CDTES").address(0x181B2).build()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstantBuilder {
+    title: String,
+    description: Option<String>,
+    uid: Option<u32>,
+    categories: Vec<u32>,
+    address: Option<u32>,
+    element_bits: u32,
+    type_flags: Option<u32>,
+    equation: String,
+    units: Option<String>,
+    decimal_places: Option<u32>,
+    output_type: Option<u32>,
+}
+
+impl ConstantBuilder {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            description: None,
+            uid: None,
+            categories: Vec::new(),
+            address: None,
+            element_bits: 8,
+            type_flags: None,
+            equation: "X".into(),
+            units: None,
+            decimal_places: None,
+            output_type: None,
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn uid(mut self, uid: u32) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    /// Adds the constant to a category, may be called more than once.
+    pub fn category(mut self, category: u32) -> Self {
+        self.categories.push(category);
+        self
+    }
+
+    /// Address of the value.
+    pub fn address(mut self, address: u32) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// Size of the value, 8 by default.
+    pub fn element_bits(mut self, bits: u32) -> Self {
+        self.element_bits = bits;
+        self
+    }
+
+    /// `mmedtypeflags` of the value.
+    pub fn type_flags(mut self, flags: u32) -> Self {
+        self.type_flags = Some(flags);
+        self
+    }
+
+    /// Conversion from the stored value, `X` by default.
+    pub fn equation(mut self, equation: impl Into<String>) -> Self {
+        self.equation = equation.into();
+        self
+    }
+
+    pub fn units(mut self, units: impl Into<String>) -> Self {
+        self.units = Some(units.into());
+        self
+    }
+
+    pub fn decimal_places(mut self, places: u32) -> Self {
+        self.decimal_places = Some(places);
+        self
+    }
+
+    pub fn output_type(mut self, output_type: u32) -> Self {
+        self.output_type = Some(output_type);
+        self
+    }
+
+    /// Checks the configuration and builds the constant.
+    pub fn build(&self) -> Result<XDFConstant, BuildError> {
+        let address = self.address.ok_or(BuildError::MissingAddress)?;
+        check_data(address, 1, self.element_bits)?;
+        check_equation(&self.equation)?;
+
+        Ok(XDFConstant {
+            title: Some(self.title.clone()),
+            description: self.description.clone(),
+            catmem: category_memberships(&self.categories),
+            uid: self.uid,
+            embedded_data: Some(EmbeddedData {
+                mmedtypeflags: self.type_flags,
+                mmedaddress: Some(address),
+                mmedelementsizebits: Some(self.element_bits),
+                mmedmajorstridebits: Some(0),
+                mmedminorstridebits: Some(0),
+                ..Default::default()
+            }),
+            decimalplaces: self.decimal_places,
+            datatype: Some(0),
+            unittype: Some(0),
+            outputtype: self.output_type,
+            unit: self.units.clone(),
+            dalink_index: Some(0),
+            math: Some(math(&self.equation)),
+            ..Default::default()
+        })
+    }
+}
+
+/// Checks that `count` elements of `element_bits` starting at `address` fit in the address space.
+fn check_data(address: u32, count: u32, element_bits: u32) -> Result<(), BuildError> {
+    if !matches!(element_bits, 8 | 16 | 32) {
+        return Err(BuildError::ElementSize(element_bits));
+    }
+    let end = u64::from(address) + u64::from(count) * u64::from(element_bits / 8);
+    if end > 1 << 32 {
+        return Err(BuildError::AddressOverflow);
+    }
+    Ok(())
+}
+
+/// Checks that the equation is one `math` can evaluate.
+fn check_equation(equation: &str) -> Result<(), BuildError> {
+    if equation.trim().is_empty() {
+        return Err(BuildError::EmptyEquation);
+    }
+    math(equation).equation()?;
+    Ok(())
+}
+
+/// MATH element for an equation, the variable is `X` unless the equation only uses a lowercase `x`.
+fn math(equation: &str) -> Math {
+    let var = if !equation.contains('X') && equation.contains('x') {
+        "x"
+    } else {
+        "X"
+    };
+    Math {
+        vars: vec![var.into()],
+        expression: Some(equation.into()),
+    }
+}
+
+fn category_memberships(categories: &[u32]) -> Vec<CategoryMem> {
+    categories
+        .iter()
+        .zip(0..)
+        .map(|(category, index)| CategoryMem {
+            index: Some(index),
+            category: Some(*category),
+        })
+        .collect()
+}
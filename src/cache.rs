@@ -0,0 +1,471 @@
+//! Compact binary snapshots of parsed definitions, for tools that load the same XDFs over and over.
+//! A snapshot records a hash of the XDF it was parsed from and is ignored once the XDF changes.
+//!
+//! Layout: `XDFS` magic, layout id, source length and FNV-1a hash (u64 LE each), then the definition.
+//! Numbers are LEB128 varints (zigzag encoded when signed), floats 4 bytes LE, strings and lists a varint length followed by their contents,
+//! optional values a 0/1 tag followed by the value. Fields are written in declaration order.
+//!
+//! The layout id is a hash of the name and type of every field written, worked out at compile time,
+//! so snapshots written before a type gains, loses or reorders fields are ignored rather than misread.
+
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{data_types::*, error::Error};
+
+const MAGIC: &[u8; 4] = b"XDFS";
+
+/// Continues a 64 bit FNV-1a hash over `data`.
+const fn fnv(mut hash: u64, data: &[u8]) -> u64 {
+    let mut i = 0;
+    while i < data.len() {
+        hash = (hash ^ data[i] as u64).wrapping_mul(0x0100_0000_01B3);
+        i += 1;
+    }
+    hash
+}
+
+/// 64 bit FNV-1a hash.
+fn hash(data: &[u8]) -> u64 {
+    fnv(0xCBF2_9CE4_8422_2325, data)
+}
+
+/// Layout id of a type named `name` made up of parts with the given layout ids, see `Snapshot::LAYOUT`.
+const fn layout(name: &str, parts: &[u64]) -> u64 {
+    let mut hash = fnv(0xCBF2_9CE4_8422_2325, name.as_bytes());
+    let mut i = 0;
+    while i < parts.len() {
+        hash = fnv(hash, &parts[i].to_le_bytes());
+        i += 1;
+    }
+    hash
+}
+
+/// Layout id of a field, found through an accessor so the field's type does not have to be spelled out.
+const fn field<S, T: Snapshot>(name: &str, _: fn(&S) -> &T) -> u64 {
+    layout(name, &[T::LAYOUT])
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+struct Decoder<'a> {
+    input: &'a [u8],
+}
+
+impl Decoder<'_> {
+    fn bytes(&mut self, len: usize) -> io::Result<&[u8]> {
+        if len > self.input.len() {
+            return Err(invalid("snapshot is truncated"));
+        }
+        let (bytes, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.bytes(1)?[0];
+            value |= u64::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("snapshot number is too long"))
+    }
+
+    fn len(&mut self) -> io::Result<usize> {
+        let len = usize::try_from(self.varint()?).map_err(|_| invalid("bad length"))?;
+        // Every item takes at least a byte, anything longer cannot be valid
+        if len > self.input.len() {
+            return Err(invalid("snapshot is truncated"));
+        }
+        Ok(len)
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Conversion to and from the snapshot layout.
+trait Snapshot: Sized {
+    /// Identifies how values of the type are written, any change to the encoding must change it.
+    const LAYOUT: u64;
+
+    fn encode(&self, out: &mut Vec<u8>);
+    fn decode(input: &mut Decoder) -> io::Result<Self>;
+}
+
+impl Snapshot for u8 {
+    const LAYOUT: u64 = layout("u8", &[]);
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+
+    fn decode(input: &mut Decoder) -> io::Result<Self> {
+        Ok(input.bytes(1)?[0])
+    }
+}
+
+impl Snapshot for u32 {
+    const LAYOUT: u64 = layout("u32 varint", &[]);
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_varint(out, u64::from(*self));
+    }
+
+    fn decode(input: &mut Decoder) -> io::Result<Self> {
+        u32::try_from(input.varint()?).map_err(|_| invalid("number out of range"))
+    }
+}
+
+impl Snapshot for i32 {
+    const LAYOUT: u64 = layout("i32 zigzag varint", &[]);
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_varint(out, u64::from((*self << 1 ^ *self >> 31) as u32));
+    }
+
+    fn decode(input: &mut Decoder) -> io::Result<Self> {
+        let zigzag = u32::decode(input)?;
+        Ok((zigzag >> 1) as i32 ^ -((zigzag & 1) as i32))
+    }
+}
+
+impl Snapshot for f32 {
+    const LAYOUT: u64 = layout("f32 LE", &[]);
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend(self.to_le_bytes());
+    }
+
+    fn decode(input: &mut Decoder) -> io::Result<Self> {
+        let bytes = input.bytes(4)?;
+        Ok(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+impl Snapshot for String {
+    const LAYOUT: u64 = layout("UTF-8 string", &[]);
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_varint(out, self.len() as u64);
+        out.extend(self.as_bytes());
+    }
+
+    fn decode(input: &mut Decoder) -> io::Result<Self> {
+        let len = input.len()?;
+        String::from_utf8(input.bytes(len)?.to_vec()).map_err(|_| invalid("bad string"))
+    }
+}
+
+impl<T: Snapshot> Snapshot for Option<T> {
+    const LAYOUT: u64 = layout("Option", &[T::LAYOUT]);
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(v) => {
+                out.push(1);
+                v.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut Decoder) -> io::Result<Self> {
+        match u8::decode(input)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(input)?)),
+            _ => Err(invalid("bad option tag")),
+        }
+    }
+}
+
+impl<T: Snapshot> Snapshot for Vec<T> {
+    const LAYOUT: u64 = layout("Vec", &[T::LAYOUT]);
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_varint(out, self.len() as u64);
+        for v in self {
+            v.encode(out);
+        }
+    }
+
+    fn decode(input: &mut Decoder) -> io::Result<Self> {
+        let len = input.len()?;
+        (0..len).map(|_| T::decode(input)).collect()
+    }
+}
+
+impl<A: Snapshot, B: Snapshot> Snapshot for (A, B) {
+    const LAYOUT: u64 = layout("tuple", &[A::LAYOUT, B::LAYOUT]);
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+
+    fn decode(input: &mut Decoder) -> io::Result<Self> {
+        Ok((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+impl Snapshot for RawNode {
+    // Elements are named rather than hashed, as they contain nodes themselves
+    const LAYOUT: u64 = layout("RawNode RawElement", &[String::LAYOUT]);
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            RawNode::Element(e) => {
+                out.push(0);
+                e.encode(out);
+            }
+            RawNode::Text(t) => {
+                out.push(1);
+                t.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut Decoder) -> io::Result<Self> {
+        match u8::decode(input)? {
+            0 => Ok(RawNode::Element(RawElement::decode(input)?)),
+            1 => Ok(RawNode::Text(String::decode(input)?)),
+            _ => Err(invalid("bad node tag")),
+        }
+    }
+}
+
+/// Implements `Snapshot` for a struct by writing each field in turn.
+macro_rules! snapshot {
+    ($name:ident { $($field:ident),* $(,)? }) => {
+        impl Snapshot for $name {
+            const LAYOUT: u64 = layout(
+                stringify!($name),
+                &[$(field(stringify!($field), |v: &$name| &v.$field)),*],
+            );
+
+            fn encode(&self, out: &mut Vec<u8>) {
+                $(self.$field.encode(out);)*
+            }
+
+            fn decode(input: &mut Decoder) -> io::Result<Self> {
+                Ok(Self {
+                    $($field: Snapshot::decode(input)?,)*
+                })
+            }
+        }
+    };
+}
+
+snapshot!(Region {
+    rtype,
+    startaddress,
+    size,
+    regionflags
+});
+snapshot!(Defaults {
+    datasizeinbits,
+    sigdigits,
+    outputtype,
+    signed,
+    lsbfirst,
+    float
+});
+snapshot!(Category { index, name });
+snapshot!(XDFHeader {
+    deftitle,
+    description,
+    fileversion,
+    author,
+    baseoffset,
+    defaults,
+    region,
+    flags,
+    category,
+    extras
+});
+snapshot!(Label { index, value });
+snapshot!(EmbedInfo { etype, linkobjid });
+snapshot!(RawElement {
+    name,
+    attributes,
+    children
+});
+snapshot!(ExtraChild { position, element });
+snapshot!(Extras {
+    attributes,
+    children
+});
+snapshot!(Math { vars, expression });
+snapshot!(CategoryMem { index, category });
+snapshot!(EmbeddedData {
+    mmedtypeflags,
+    mmedaddress,
+    mmedelementsizebits,
+    mmedmajorstridebits,
+    mmedminorstridebits,
+    mmedrowcount,
+    mmedcolcount,
+    extras
+});
+snapshot!(XDFConstant {
+    title,
+    description,
+    catmem,
+    uid,
+    embedded_data,
+    decimalplaces,
+    datatype,
+    unittype,
+    outputtype,
+    unit,
+    dalink_index,
+    math,
+    extras
+});
+snapshot!(XDFAxis {
+    id,
+    uid,
+    embeddeddata,
+    datatype,
+    unittype,
+    dalink_index,
+    math,
+    count,
+    labels,
+    min,
+    max,
+    outputtype,
+    decimalplaces,
+    unit,
+    embedinfo,
+    extras
+});
+snapshot!(XDFTable {
+    title,
+    uid,
+    flags,
+    catmem,
+    description,
+    axis,
+    extras
+});
+snapshot!(XDFPatchEntry {
+    name,
+    address,
+    datasize,
+    patchdata,
+    basedata
+});
+snapshot!(XDFPatch {
+    title,
+    description,
+    catmem,
+    uid,
+    entries
+});
+snapshot!(XDFFlag {
+    title,
+    description,
+    catmem,
+    uid,
+    embedded_data,
+    mask
+});
+snapshot!(ChecksumRegion {
+    datastart,
+    dataend,
+    datasizebits,
+    storeaddress,
+    calculationmethod
+});
+snapshot!(XDFChecksum {
+    title,
+    description,
+    catmem,
+    uid,
+    regions
+});
+snapshot!(XDFFormat {
+    version,
+    tables,
+    constants,
+    patches,
+    flags,
+    checksums,
+    header
+});
+
+/// Where `XDFFormat::from_path_cached` keeps the snapshot of an XDF, `name.xdf` becomes `name.xdf.snapshot`.
+pub fn snapshot_path(path: impl AsRef<Path>) -> PathBuf {
+    let mut name = path.as_ref().as_os_str().to_owned();
+    name.push(".snapshot");
+    PathBuf::from(name)
+}
+
+impl XDFFormat {
+    /// Writes a snapshot of the definition, `source` is the XDF it was parsed from.
+    pub fn write_snapshot<W: Write>(&self, source: &[u8], mut writer: W) -> io::Result<()> {
+        let mut out = Vec::new();
+        out.extend(MAGIC);
+        out.extend(Self::LAYOUT.to_le_bytes());
+        out.extend((source.len() as u64).to_le_bytes());
+        out.extend(hash(source).to_le_bytes());
+        self.encode(&mut out);
+        writer.write_all(&out)
+    }
+
+    /// Reads a snapshot written by `write_snapshot`.
+    /// Returns `None` if it was written from a different `source` or by an incompatible version of this crate.
+    pub fn read_snapshot<R: Read>(source: &[u8], mut reader: R) -> io::Result<Option<Self>> {
+        let mut snapshot = Vec::new();
+        reader.read_to_end(&mut snapshot)?;
+        let mut input = Decoder { input: &snapshot };
+        if input.bytes(4)? != MAGIC {
+            return Err(invalid("not an XDF snapshot"));
+        }
+        let expected = [
+            Self::LAYOUT.to_le_bytes(),
+            (source.len() as u64).to_le_bytes(),
+            hash(source).to_le_bytes(),
+        ]
+        .concat();
+        if input.bytes(expected.len())? != expected {
+            return Ok(None);
+        }
+        let format = Self::decode(&mut input)?;
+        if !input.input.is_empty() {
+            return Err(invalid("trailing data after snapshot"));
+        }
+        Ok(Some(format))
+    }
+
+    /// Opens an XDF file, using the snapshot next to it when it is up to date.
+    /// Otherwise the XDF is parsed and the snapshot rewritten, failing to write the snapshot is not an error.
+    pub fn from_path_cached(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let source = fs::read(&path)?;
+        let snapshot = snapshot_path(&path);
+        if let Ok(file) = File::open(&snapshot) {
+            if let Ok(Some(format)) = Self::read_snapshot(&source, file) {
+                return Ok(format);
+            }
+        }
+        let format = Self::parse(source.as_slice())?;
+        if let Ok(file) = File::create(&snapshot) {
+            let _ = format.write_snapshot(&source, file);
+        }
+        Ok(format)
+    }
+}
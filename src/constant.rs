@@ -0,0 +1,71 @@
+//! Reading and editing constants in a bin, in the units shown to the user.
+//! Typical edits are scalar limits and codewords such as CDTES.
+
+use crate::{
+    bin::{BinError, BinImage},
+    data_types::*,
+    math::{equation, Equation},
+};
+
+/// Shows `value` the way an item with these display settings would, followed by the unit if there is one.
+/// Floats use `decimals` places, 2 when not given. Missing or unknown output types show as floats.
+pub(crate) fn format_value(
+    value: f64,
+    decimals: Option<u32>,
+    output: Option<u32>,
+    unit: Option<&str>,
+) -> String {
+    let whole = value.round() as i64;
+    let text = match output.and_then(OutputType::from_value) {
+        Some(OutputType::Integer) => whole.to_string(),
+        Some(OutputType::Hex) if whole < 0 => format!("-0x{:X}", whole.unsigned_abs()),
+        Some(OutputType::Hex) => format!("0x{whole:X}"),
+        Some(OutputType::String) => whole
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|b| *b == 0)
+            .map(char::from)
+            .collect(),
+        Some(OutputType::Float) | None => {
+            format!("{:.*}", decimals.unwrap_or(2) as usize, value)
+        }
+    };
+    match unit.filter(|u| !u.trim().is_empty()) {
+        Some(unit) => format!("{text} {unit}"),
+        None => text,
+    }
+}
+
+impl XDFConstant {
+    fn data(&self) -> Result<&EmbeddedData, BinError> {
+        self.embedded_data.as_ref().ok_or(BinError::MissingAddress)
+    }
+
+    fn equation(&self) -> Result<Equation, BinError> {
+        Ok(equation(self.math.as_ref())?)
+    }
+
+    /// Value of the constant in `bin`, with its math applied.
+    pub fn read(&self, bin: &BinImage) -> Result<f64, BinError> {
+        let raw = bin.read(self.data()?)?;
+        Ok(self.equation()?.eval(raw[0]))
+    }
+
+    /// Stores `value` in `bin`: the math is inverted and the result rounded to the nearest value the element can hold.
+    /// Fails without changing `bin` if the stored value would not fit the element.
+    pub fn write(&self, bin: &mut BinImage, value: f64) -> Result<(), BinError> {
+        let data = self.data()?;
+        let raw = self.equation()?.invert(value)?;
+        bin.write(data, &[bin.element(data)?.nearest(raw)])
+    }
+
+    /// Shows a value of the constant using its `decimalplaces`, `outputtype` and `unit`.
+    pub fn format(&self, value: f64) -> String {
+        format_value(
+            value,
+            self.decimalplaces,
+            self.outputtype,
+            self.unit.as_deref(),
+        )
+    }
+}
@@ -5,19 +5,45 @@
 //! Target files have a max size of 1MB, so everything fits in a 32bit address space.
 //! Because of this, all addresses and sizes have a datatype of u32, in some cases these will be later converted to usize for use in Rust code, but that is not in scope for this module.
 
-/// How values are shown to the user. These mappings to numbers may be wrong.
+use std::ops::Range;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::bin::{BinError, TYPE_COLUMN_MAJOR};
+
+/// How values are shown to the user, the numbers are the `outputtype` values written by TunerPro.
 #[derive(Debug, Clone, PartialEq, Copy, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 #[repr(u8)]
 pub enum OutputType {
-    Float = 0,
-    Integer = 1,
-    Hex = 2,
-    String = 3,
+    Float = 1,
+    Integer = 2,
+    Hex = 3,
+    /// Bytes of the value as ASCII characters
+    String = 4,
+}
+
+impl OutputType {
+    /// Output type for an `outputtype` value, `None` for values TunerPro does not define.
+    pub fn from_value(value: u32) -> Option<Self> {
+        match value {
+            1 => Some(Self::Float),
+            2 => Some(Self::Integer),
+            3 => Some(Self::Hex),
+            4 => Some(Self::String),
+            _ => None,
+        }
+    }
 }
 
 /// Purpose unknown. Used in XDFHEADER
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Region {
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub rtype: Option<u32>,
     pub startaddress: Option<u32>,
     pub size: Option<u32>,
@@ -25,7 +51,9 @@ pub struct Region {
 }
 
 /// Default configuration for items as defined in XDFHEADER
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Defaults {
     pub datasizeinbits: Option<u32>,
     pub sigdigits: Option<u32>,
@@ -36,7 +64,9 @@ pub struct Defaults {
 }
 
 /// Data category for displaying XDF items
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Category {
     pub index: Option<u32>,
     pub name: Option<String>,
@@ -44,7 +74,9 @@ pub struct Category {
 
 /// Header for XDF files, contains basic info such as origin of file.
 /// Definition incomplete.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct XDFHeader {
     pub deftitle: Option<String>,
     pub description: Option<String>,
@@ -56,10 +88,14 @@ pub struct XDFHeader {
     pub flags: Option<u32>,
     // Could be array?
     pub category: Vec<Category>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Extras::is_empty"))]
+    pub extras: Extras,
 }
 
 /// Labels for XDFAXIS
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Label {
     pub index: Option<u32>,
     /// This may be wrong, but it seems these are only used for user defined values anyways, not calculations.
@@ -67,10 +103,13 @@ pub struct Label {
 }
 
 #[derive(Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct EmbedInfo {
     /// Unknown Purpose
     /// linked axis: 3
     /// inplace definition with data location: 1
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub etype: Option<u32>,
     /// Unique ID of the table describing the actual data
     /// Axis to use in the actual table seems to be the one with no `uniqueid` definition rather than `uniqueid="0x0"`
@@ -79,6 +118,8 @@ pub struct EmbedInfo {
 
 /// Individual elements and their sub elements that can be found in an XML formatted XDF filke
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "element", content = "value"))]
 pub enum XDFElement {
     End(String),
     Title(String),
@@ -228,12 +269,149 @@ pub enum XDFElement {
     ///   </XDFTABLE>
     /// ```
     XDFTable(XDFTable),
+    /// Code patch, a set of byte ranges that can be swapped between their stock and patched contents.
+    /// example: ```xml
+    ///   <XDFPATCH uniqueid="0x2A1">
+    ///     <title>Disable rear O2 sensors</title>
+    ///     <description>Patches out the post-cat lambda check</description>
+    ///     <CATEGORYMEM index="0" category="3" />
+    ///     <XDFPATCHENTRY name="Jump" address="0x1A2B4" datasize="0x2" patchdata="0DFB" basedata="3DF7" />
+    ///   </XDFPATCH>
+    /// ```
+    XDFPatch(XDFPatch),
+    /// Single byte range of an XDFPATCH
+    /// example: `<XDFPATCHENTRY name="Jump" address="0x1A2B4" datasize="0x2" patchdata="0DFB" basedata="3DF7" />`
+    XDFPatchEntry(XDFPatchEntry),
+    /// Bit mask selecting the bits of a flag inside the element described by EMBEDDEDDATA
+    /// Found in XDFFLAG
+    /// example: `<mask>0x4</mask>`
+    Mask(u32),
+    /// Single bit switch (e.g. a codeword bit), shown as a checkbox rather than a value.
+    /// example: ```xml
+    ///   <XDFFLAG uniqueid="0x1A3C">
+    ///     <title>CDTES</title>
+    ///     <description>Codeword: turn off tank venting diagnosis</description>
+    ///     <CATEGORYMEM index="0" category="27" />
+    ///     <EMBEDDEDDATA mmedaddress="0x181B2" mmedelementsizebits="8" mmedmajorstridebits="0" mmedminorstridebits="0" />
+    ///     <mask>0x1</mask>
+    ///   </XDFFLAG>
+    /// ```
+    XDFFlag(XDFFlag),
+    /// First address (inclusive) of the data covered by a checksum
+    /// Found in the REGION of an XDFCHECKSUM
+    /// example: `<datastart>0x10000</datastart>`
+    DataStart(u32),
+    /// Last address (inclusive) of the data covered by a checksum
+    /// Found in the REGION of an XDFCHECKSUM
+    /// example: `<dataend>0x1FFFF</dataend>`
+    DataEnd(u32),
+    /// Size in bits of the values summed by a checksum
+    /// Found in the REGION of an XDFCHECKSUM
+    /// example: `<datasizebits>0x10</datasizebits>`
+    DataSizeBits(u32),
+    /// Address the calculated checksum is stored at
+    /// Found in the REGION of an XDFCHECKSUM
+    /// example: `<storeaddress>0x1FFFC</storeaddress>`
+    StoreAddress(u32),
+    /// Algorithm used to calculate a checksum, meaning of the values is defined by TunerPro
+    /// Found in the REGION of an XDFCHECKSUM
+    /// example: `<calculationmethod>0x1</calculationmethod>`
+    CalculationMethod(u32),
+    /// REGION element of an XDFCHECKSUM, unlike the REGION in XDFHEADER this stores its values as child elements.
+    ChecksumRegion(ChecksumRegion),
+    /// Checksum definition, describes which data is summed and where the result is stored.
+    /// example: ```xml
+    ///   <XDFCHECKSUM uniqueid="0x5A0">
+    ///     <title>Main checksum</title>
+    ///     <REGION>
+    ///       <datastart>0x10000</datastart>
+    ///       <dataend>0x1FFFB</dataend>
+    ///       <datasizebits>0x10</datasizebits>
+    ///       <storeaddress>0x1FFFC</storeaddress>
+    ///       <calculationmethod>0x1</calculationmethod>
+    ///     </REGION>
+    ///   </XDFCHECKSUM>
+    /// ```
+    XDFChecksum(XDFChecksum),
+    /// Element not known by the parser, only produced when parsing with `ParseOptions { strict: false }`
+    Unknown(RawElement),
+}
+
+/// Unparsed XML content, used to keep elements the parser does not understand.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RawNode {
+    Element(RawElement),
+    Text(String),
+}
+
+/// Unparsed XML element, names are kept as written in the document.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RawElement {
+    pub name: String,
+    /// Attribute name and value pairs in document order
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<RawNode>,
+}
+
+/// Content of an element that the parser does not understand, kept so it can be written back out.
+/// Only the header, tables, axes, constants and their `EMBEDDEDDATA` keep unknown content,
+/// unknown children of other elements are reported as warnings and dropped.
+///
+/// Unknown child elements fail the parse unless `ParseOptions::strict` is turned off,
+/// so `children` is always empty for documents parsed with the default options.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Extras {
+    /// Unknown attribute name and value pairs in document order
+    pub attributes: Vec<(String, String)>,
+    /// Unknown child elements in document order
+    pub children: Vec<ExtraChild>,
+}
+
+/// Unknown child element along with its place among the children of its parent.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ExtraChild {
+    /// Number of child elements kept before it, known or not.
+    /// The writer puts it back after as many children, so it keeps its place while the known children are unchanged.
+    pub position: u32,
+    pub element: RawElement,
+}
+
+impl Extras {
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty() && self.children.is_empty()
+    }
+}
+
+impl RawElement {
+    pub(crate) fn new(
+        name: &xml::name::OwnedName,
+        attributes: &[xml::attribute::OwnedAttribute],
+    ) -> Self {
+        Self {
+            name: name.local_name.clone(),
+            attributes: attributes
+                .iter()
+                .map(|a| (a.name.local_name.clone(), a.value.clone()))
+                .collect(),
+            children: Vec::new(),
+        }
+    }
 }
 
 /// Operations to perform on a value before displaying or writing it.
 /// Seems ususally have a single variable (X) which is the value stored in the bin.
 /// There is generally only a factor and or a constant to be applied.
 #[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Math {
     /// Variables used in equation, usually just X
     pub vars: Vec<String>,
@@ -243,54 +421,128 @@ pub struct Math {
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct CategoryMem {
     pub index: Option<u32>,
     pub category: Option<u32>,
 }
 
 /// Describes how and where the data is stored in the bin file, if mmedaddress is undefined it is not stored or read from the bin.
-/// When rowcount and colcount are both greater than 1, each row is written sequentially.
-/// For example with 8 rows of 16 columns, you would write each of the 16 column values for a row before writing the next row.
-/// Data is stored in a one dimensional array :)
-#[derive(Debug, Default, Clone, PartialEq, Copy)]
+///
+/// Data is a grid of `mmedrowcount` rows and `mmedcolcount` columns (1 when missing), stored as a series of lines:
+/// rows by default, columns when `mmedtypeflags` has the column-major bit (`bin::TYPE_COLUMN_MAJOR`) set.
+/// Data with a single row or column is stored as lines of one element each, whatever its orientation.
+/// - `mmedminorstridebits`: distance from one element of a line to the next, the element size by default.
+/// - `mmedmajorstridebits`: distance from the start of one line to the next, the minor stride times the line length by default.
+///
+/// Strides of 0 or less use the default (TunerPro writes -32 for axes without data), others must be whole bytes.
+/// For example 8 rows of 16 columns, row-major without strides: the 16 values of the first row, then those of the next row.
+/// Column-major: the 8 values of the first column, then those of the next column.
+/// Strides larger than the defaults leave gaps, used for padded or interleaved layouts.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct EmbeddedData {
     /// Base address (relative to start of file) of data
     pub mmedaddress: Option<u32>,
     /// Size in bits of each element
     pub mmedelementsizebits: Option<u32>,
-    pub mmedmajorstridebits: Option<i32>, // ?
-    pub mmedminorstridebits: Option<i32>, // ?
-    /// Seems to only be present when in XDFCONSTANT
-    pub mmedtypeflags: Option<u32>, // ?
+    /// Bits between the starts of consecutive lines
+    pub mmedmajorstridebits: Option<i32>,
+    /// Bits between consecutive elements of a line
+    pub mmedminorstridebits: Option<i32>,
+    /// Storage format bits, see `bin::TYPE_SIGNED` and friends. Header DEFAULTS apply when missing.
+    pub mmedtypeflags: Option<u32>,
     /// Number of rows
     pub mmedrowcount: Option<u32>,
     /// Number of columns
     pub mmedcolcount: Option<u32>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Extras::is_empty"))]
+    pub extras: Extras,
+}
+
+/// Stride in bytes, `None` for the default.
+fn stride(bits: Option<i32>) -> Result<Option<u64>, BinError> {
+    match bits {
+        Some(bits) if bits > 0 && bits % 8 == 0 => Ok(Some(bits as u64 / 8)),
+        Some(bits) if bits > 0 => Err(BinError::Stride(bits)),
+        _ => Ok(None),
+    }
+}
+
+impl EmbeddedData {
+    /// Offset from `mmedaddress` of each element, row by row whatever the storage order, for elements of `size` bytes.
+    pub fn element_offsets(&self, size: u64) -> Result<Vec<u64>, BinError> {
+        let rows = u64::from(self.mmedrowcount.unwrap_or(1).max(1));
+        let cols = u64::from(self.mmedcolcount.unwrap_or(1).max(1));
+        let column_major = self.mmedtypeflags.unwrap_or(0) & TYPE_COLUMN_MAJOR != 0;
+        let (lines, len) = if column_major {
+            (cols, rows)
+        } else {
+            (rows, cols)
+        };
+        let vector = lines == 1 || len == 1;
+        let minor = stride(self.mmedminorstridebits)?.unwrap_or(size);
+        let major =
+            stride(self.mmedmajorstridebits)?.unwrap_or(if vector { minor } else { minor * len });
+        Ok((0..rows)
+            .flat_map(|row| (0..cols).map(move |col| (row, col)))
+            .map(|(row, col)| {
+                let (line, position) = if vector {
+                    (row * cols + col, 0)
+                } else if column_major {
+                    (col, row)
+                } else {
+                    (row, col)
+                };
+                line * major + position * minor
+            })
+            .collect())
+    }
+
+    /// Bytes of the bin spanned by the data, assuming 8 bit elements and a single row and column where those are not defined.
+    /// Gaps left by strides are included. `None` when the data has no address or invalid strides.
+    pub fn byte_range(&self) -> Option<Range<u64>> {
+        let start = u64::from(self.mmedaddress?);
+        let element = u64::from(self.mmedelementsizebits.unwrap_or(8).div_ceil(8));
+        let offsets = self.element_offsets(element).ok()?;
+        let first = offsets.iter().min()?;
+        let last = offsets.iter().max()?;
+        Some(start + first..start + last + element)
+    }
 }
 
 /// Single value constant, unsure of practical difference between this and a 0x0x1 table.
 #[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct XDFConstant {
     pub title: Option<String>,
     pub description: Option<String>,
     pub catmem: Vec<CategoryMem>, // ?
-    pub uid: Option<String>,      // uniqueid, doesnt actually seem to be unique
+    pub uid: Option<u32>,         // uniqueid, not always unique, see `ids`
     pub embedded_data: Option<EmbeddedData>,
     pub decimalplaces: Option<u32>,
     pub datatype: Option<u32>,   // unknown
     pub unittype: Option<u32>,   // unknown
-    pub outputtype: Option<u32>, // unknown
+    pub outputtype: Option<u32>, // see `OutputType`
     pub unit: Option<String>,
     pub dalink_index: Option<u32>, // unknown
     pub math: Option<Math>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Extras::is_empty"))]
+    pub extras: Extras,
 }
 
 /// Axis definition for a table, generally contains a series of labels (non stored values) or a data location (values stored in bin)
 /// This is likely only used for display purposes.
 #[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct XDFAxis {
     pub id: Option<String>, // name
     pub uid: Option<u32>,   // uniqueid, doesnt actually seem to be unique
+    #[cfg_attr(feature = "serde", serde(rename = "embedded_data"))]
     pub embeddeddata: Option<EmbeddedData>,
     pub datatype: Option<u32>,
     pub unittype: Option<u32>,
@@ -301,30 +553,118 @@ pub struct XDFAxis {
     // above this line probably required
     pub min: Option<f32>,           // min value
     pub max: Option<f32>,           // max value
-    pub outputtype: Option<u32>,    // unknown
+    pub outputtype: Option<u32>,    // see `OutputType`
     pub decimalplaces: Option<u32>, // how many dceimal places to display, doenst seem to effect output
     pub unit: Option<String>,
     pub embedinfo: Option<EmbedInfo>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Extras::is_empty"))]
+    pub extras: Extras,
 }
 
 /// Table, contains multiple (three seems to be common) axis
 /// For some reason, instead of having multiple editeable axis, some tables will have a sepearate linked table defining an editable (stored) axis.
 /// TVUB is an example of this with its axis data being stored in TVUB_AXIS.
 #[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct XDFTable {
     pub title: Option<String>, // obvious
-    pub uid: Option<u32>,      // bitcount?
+    pub uid: Option<u32>,      // uniqueid, not always unique, see `ids`
     pub flags: Option<u32>,    // bitcount? purpose unknown
     pub catmem: Vec<CategoryMem>,
     pub description: Option<String>,
     pub axis: Vec<XDFAxis>, // duh
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Extras::is_empty"))]
+    pub extras: Extras,
+}
+
+impl XDFTable {
+    /// First axis with the given id, usually `"x"`, `"y"` or `"z"`.
+    pub fn axis_by_id(&self, id: &str) -> Option<&XDFAxis> {
+        self.axis.iter().find(|a| a.id.as_deref() == Some(id))
+    }
+}
+
+/// Single byte range of a patch, `patchdata` is written to the bin to apply the patch, `basedata` to remove it.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct XDFPatchEntry {
+    pub name: Option<String>,
+    /// Base address (relative to start of file) of the patched bytes
+    pub address: Option<u32>,
+    /// Number of bytes in the entry, should match the length of `patchdata` and `basedata`
+    pub datasize: Option<u32>,
+    /// Bytes written when the patch is applied, stored in the XDF as a hex string
+    pub patchdata: Option<Vec<u8>>,
+    /// Stock bytes expected when the patch is not applied, stored in the XDF as a hex string
+    pub basedata: Option<Vec<u8>>,
+}
+
+/// Code patch, a named group of byte ranges that are switched between stock and patched contents together.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct XDFPatch {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub catmem: Vec<CategoryMem>,
+    pub uid: Option<u32>, // uniqueid
+    pub entries: Vec<XDFPatchEntry>,
+}
+
+/// Single bit switch, the flag is set when all bits of `mask` are set in the element described by `embedded_data`.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct XDFFlag {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub catmem: Vec<CategoryMem>,
+    pub uid: Option<u32>, // uniqueid
+    pub embedded_data: Option<EmbeddedData>,
+    pub mask: Option<u32>,
+}
+
+/// Range of data covered by a checksum and where the result is stored.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ChecksumRegion {
+    /// First address (inclusive) of the summed data
+    pub datastart: Option<u32>,
+    /// Last address (inclusive) of the summed data
+    pub dataend: Option<u32>,
+    /// Size in bits of each summed value
+    pub datasizebits: Option<u32>,
+    /// Address the result is written to
+    pub storeaddress: Option<u32>,
+    /// Algorithm used, values are defined by TunerPro
+    pub calculationmethod: Option<u32>,
+}
+
+/// Checksum definition, usually a single region but TunerPro allows several.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct XDFChecksum {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub catmem: Vec<CategoryMem>,
+    pub uid: Option<u32>, // uniqueid
+    pub regions: Vec<ChecksumRegion>,
 }
 
 /// A complete XDF file
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct XDFFormat {
     pub version: Option<String>,
     pub tables: Vec<XDFTable>,
     pub constants: Vec<XDFConstant>,
+    pub patches: Vec<XDFPatch>,
+    pub flags: Vec<XDFFlag>,
+    pub checksums: Vec<XDFChecksum>,
     pub header: Option<XDFHeader>,
 }
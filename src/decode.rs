@@ -0,0 +1,118 @@
+//! Decoding of attribute values and element text into typed values.
+//! TunerPro is not consistent in how it writes numbers, so every numeric type accepts:
+//! - surrounding whitespace
+//! - decimal (`16`, `-32`) and hex with either prefix case (`0x1EF9E`, `0X1ef9e`, `-0x20`)
+//! - floats with no fractional part for integer types (`16.000000`)
+//! - any of the above for float types (`255.000000`, `1e-3`, `0xFF`)
+//!
+//! Byte strings (XDFPATCHENTRY data) are pairs of hex digits without a prefix, e.g. `0DFB`.
+
+use xml::attribute::OwnedAttribute;
+
+use crate::error::{Error, ErrorKind};
+
+/// A value that can be decoded from the text of an attribute or element.
+pub(crate) trait Decode: Sized {
+    /// Returns `None` if the text is not a valid representation of the type.
+    fn decode(text: &str) -> Option<Self>;
+}
+
+/// Parses an optionally signed decimal or `0x`/`0X` prefixed hex integer.
+fn decode_integer(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let (negative, unsigned) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    // Only one sign, the parsers below would accept a second one
+    if unsigned.starts_with(['+', '-']) {
+        return None;
+    }
+    let magnitude = if let Some(hex) = unsigned
+        .strip_prefix("0x")
+        .or_else(|| unsigned.strip_prefix("0X"))
+    {
+        if hex.is_empty() || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Ok(int) = unsigned.parse::<i64>() {
+        int
+    } else {
+        // Integral floats such as `16.000000`
+        let float: f64 = unsigned.parse().ok()?;
+        if float.fract() != 0.0 || !float.is_finite() || float > i64::MAX as f64 {
+            return None;
+        }
+        float as i64
+    };
+    Some(if negative { -magnitude } else { magnitude })
+}
+
+impl Decode for u32 {
+    fn decode(text: &str) -> Option<Self> {
+        decode_integer(text)?.try_into().ok()
+    }
+}
+
+impl Decode for i32 {
+    fn decode(text: &str) -> Option<Self> {
+        decode_integer(text)?.try_into().ok()
+    }
+}
+
+impl Decode for f64 {
+    fn decode(text: &str) -> Option<Self> {
+        let trimmed = text.trim();
+        match trimmed.parse::<f64>() {
+            Ok(v) if v.is_finite() => Some(v),
+            Ok(_) => None,
+            Err(_) => decode_integer(trimmed).map(|v| v as f64),
+        }
+    }
+}
+
+impl Decode for f32 {
+    fn decode(text: &str) -> Option<Self> {
+        let v = f64::decode(text)? as f32;
+        v.is_finite().then_some(v)
+    }
+}
+
+impl Decode for String {
+    fn decode(text: &str) -> Option<Self> {
+        Some(text.to_string())
+    }
+}
+
+impl Decode for Vec<u8> {
+    fn decode(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if !text.len().is_multiple_of(2) || !text.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+            .collect()
+    }
+}
+
+/// Decodes text, returning `ErrorKind::BadValue` with the offending text on failure.
+pub(crate) fn decode<T: Decode>(text: &str) -> Result<T, Error> {
+    T::decode(text).ok_or_else(|| ErrorKind::BadValue(text.to_string()).into())
+}
+
+/// Decodes a named attribute, `None` if the attribute is not present.
+pub(crate) fn attr<T: Decode>(attrs: &[OwnedAttribute], name: &str) -> Result<Option<T>, Error> {
+    attrs
+        .iter()
+        .find(|a| a.name.local_name == name)
+        .map(|a| decode(&a.value).map_err(|e| e.with_attribute(name)))
+        .transpose()
+}
+
+/// Decodes a named attribute that must be present.
+pub(crate) fn required_attr<T: Decode>(attrs: &[OwnedAttribute], name: &str) -> Result<T, Error> {
+    attr(attrs, name)?.ok_or_else(|| Error::new(ErrorKind::MissingItem).with_attribute(name))
+}
@@ -0,0 +1,452 @@
+//! Format preserving editing of XDF documents.
+//! The byte span of every top level item is recorded when the document is read, when it is written back out
+//! only items that were changed, added or removed are rewritten. Everything else, including comments such as
+//! TunerPro's `<!-- Written ... -->` header, whitespace and elements the parser does not understand, is copied
+//! byte for byte, so edits produce small diffs.
+//!
+//! Spans are found by scanning the raw bytes, so documents must use an ASCII compatible encoding (anything but UTF-16).
+
+use std::{
+    fs,
+    io::{self, Write},
+    ops::Range,
+    path::Path,
+};
+
+use crate::{
+    data_types::*,
+    encoding::{declared, Encoding},
+    error::{Error, Warning},
+    event_reader,
+    parser::ParseOptions,
+    reader::XdfItem,
+    writer::item_xml,
+};
+
+/// Item of a document along with where it came from.
+#[derive(Debug, Clone)]
+struct Entry {
+    item: XdfItem,
+    /// Span of the item in the source and the item as it was read, `None` for items added since
+    source: Option<(Range<usize>, XdfItem)>,
+}
+
+/// XDF document that can be edited and written back with its original formatting.
+#[derive(Debug, Clone)]
+pub struct XdfDocument {
+    source: Vec<u8>,
+    layout: Layout,
+    entries: Vec<Entry>,
+    /// Spans of items that have been removed
+    removed: Vec<Range<usize>>,
+    version: Option<String>,
+    warnings: Vec<Warning>,
+}
+
+impl XdfDocument {
+    pub fn new(source: Vec<u8>) -> Result<Self, Error> {
+        Self::with_options(source, ParseOptions::default())
+    }
+
+    /// Reads a document, unknown content is handled according to `options` and reported in `warnings`.
+    pub fn with_options(source: Vec<u8>, options: ParseOptions) -> Result<Self, Error> {
+        if matches!(options.encoding, Encoding::Utf16Le | Encoding::Utf16Be)
+            || matches!(
+                source.as_slice(),
+                [0xFF, 0xFE, ..] | [0xFE, 0xFF, ..] | [0, _, ..] | [_, 0, ..]
+            )
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "UTF-16 documents can not be edited in place",
+            )
+            .into());
+        }
+        // Checks the whole document, so the items can be read from their spans without further errors
+        let (format, warnings) = XDFFormat::parse_with_options(source.as_slice(), options)?;
+
+        // Spans do not include the XML declaration, so the encoding it names is picked up here
+        let encoding = match options.encoding {
+            Encoding::Auto => declared(&source).unwrap_or(Encoding::Auto),
+            encoding => encoding,
+        };
+        let layout = Layout::scan(&source);
+        let mut entries = Vec::new();
+        for span in &layout.children {
+            let element = XDFElement::from_xml_with_options(
+                &mut event_reader(&source[span.clone()], encoding),
+                options,
+            )?
+            .0;
+            // Anything else is unknown content, which is kept as is with the text around the items
+            if let Ok(item) = XdfItem::from_element(element) {
+                entries.push(Entry {
+                    item: item.clone(),
+                    source: Some((span.clone(), item)),
+                });
+            }
+        }
+
+        Ok(Self {
+            source,
+            layout,
+            entries,
+            removed: Vec::new(),
+            version: format.version,
+            warnings,
+        })
+    }
+
+    /// Opens and reads an XDF file.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::new(fs::read(path)?)
+    }
+
+    /// `version` attribute of the XDFFORMAT element.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Warnings produced while reading the document.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Items of the document in document order, items added with `push` come last.
+    pub fn items(&self) -> impl Iterator<Item = &XdfItem> {
+        self.entries.iter().map(|e| &e.item)
+    }
+
+    /// Items of the document for editing, items that end up different from how they were read are rewritten by `write_to`.
+    pub fn items_mut(&mut self) -> impl Iterator<Item = &mut XdfItem> {
+        self.entries.iter_mut().map(|e| &mut e.item)
+    }
+
+    pub fn tables_mut(&mut self) -> impl Iterator<Item = &mut XDFTable> {
+        self.items_mut().filter_map(|item| match item {
+            XdfItem::Table(v) => Some(v),
+            _ => None,
+        })
+    }
+
+    pub fn constants_mut(&mut self) -> impl Iterator<Item = &mut XDFConstant> {
+        self.items_mut().filter_map(|item| match item {
+            XdfItem::Constant(v) => Some(v),
+            _ => None,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&XdfItem> {
+        self.entries.get(index).map(|e| &e.item)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut XdfItem> {
+        self.entries.get_mut(index).map(|e| &mut e.item)
+    }
+
+    /// Adds an item, it is written after the existing items.
+    pub fn push(&mut self, item: XdfItem) {
+        self.entries.push(Entry { item, source: None });
+    }
+
+    /// Removes the item at `index`, panics if the index is out of bounds.
+    pub fn remove(&mut self, index: usize) -> XdfItem {
+        let entry = self.entries.remove(index);
+        if let Some((span, _)) = entry.source {
+            self.removed.push(span);
+        }
+        entry.item
+    }
+
+    /// True if writing the document would produce something other than the source.
+    pub fn is_modified(&self) -> bool {
+        !self.removed.is_empty()
+            || self.entries.iter().any(|e| match &e.source {
+                Some((_, original)) => *original != e.item,
+                None => true,
+            })
+    }
+
+    /// The document as an `XDFFormat`, with items grouped by type.
+    pub fn to_format(&self) -> XDFFormat {
+        let mut format = XDFFormat {
+            version: self.version.clone(),
+            ..Default::default()
+        };
+        for item in self.items().cloned() {
+            format.add_item(item);
+        }
+        format
+    }
+
+    /// Writes the document, rewriting only the items that changed.
+    /// Changed items keep their position and indentation, new items are added before the end of the root element.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let newline = if self.source.windows(2).any(|w| w == b"\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        let mut edits = Vec::new();
+        for entry in &self.entries {
+            if let Some((span, original)) = &entry.source {
+                if *original != entry.item {
+                    let indent = self.indent_before(span.start);
+                    edits.push((span.clone(), item_xml(&entry.item, indent, newline)));
+                }
+            }
+        }
+        for span in &self.removed {
+            edits.push((self.line_of(span.clone()), Vec::new()));
+        }
+        let added: Vec<_> = self.entries.iter().filter(|e| e.source.is_none()).collect();
+        if !added.is_empty() {
+            edits.push(self.insertion(&added, newline));
+        }
+        edits.sort_by_key(|(span, _)| span.start);
+
+        let mut pos = 0;
+        for (span, replacement) in edits {
+            writer.write_all(&self.source[pos..span.start])?;
+            writer.write_all(&replacement)?;
+            pos = span.end;
+        }
+        writer.write_all(&self.source[pos..])?;
+        writer.flush()
+    }
+
+    /// Start of the line containing `pos`, if there is only whitespace between the two.
+    fn line_start(&self, pos: usize) -> Option<usize> {
+        let start = self.source[..pos]
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |i| i + 1);
+        self.source[start..pos]
+            .iter()
+            .all(|b| matches!(b, b' ' | b'\t'))
+            .then_some(start)
+    }
+
+    /// Whitespace between the start of the line and `pos`, empty if there is anything else before `pos` on its line.
+    fn indent_before(&self, pos: usize) -> &str {
+        match self.line_start(pos) {
+            Some(start) => {
+                std::str::from_utf8(&self.source[start..pos]).expect("checked to be ASCII")
+            }
+            None => "",
+        }
+    }
+
+    /// Widens a span to its whole line (including the line break) if nothing else is on that line.
+    fn line_of(&self, span: Range<usize>) -> Range<usize> {
+        let Some(start) = self.line_start(span.start) else {
+            return span;
+        };
+        let after = &self.source[span.end..];
+        let trailing = after
+            .iter()
+            .take_while(|b| matches!(b, b' ' | b'\t' | b'\r'))
+            .count();
+        match after.get(trailing) {
+            Some(b'\n') => start..span.end + trailing + 1,
+            None => start..self.source.len(),
+            Some(_) => span,
+        }
+    }
+
+    /// Text to insert for added items, and where to insert it.
+    fn insertion(&self, added: &[&Entry], newline: &'static str) -> (Range<usize>, Vec<u8>) {
+        let indent = self
+            .entries
+            .iter()
+            .filter_map(|e| Some(self.indent_before(e.source.as_ref()?.0.start)))
+            .find(|indent| !indent.is_empty())
+            .unwrap_or("  ");
+        let items = |out: &mut Vec<u8>, separator: &str| {
+            for entry in added {
+                out.extend_from_slice(separator.as_bytes());
+                out.extend_from_slice(&item_xml(&entry.item, indent, newline));
+            }
+        };
+
+        let end = self.layout.end.clone();
+        let mut out = Vec::new();
+        if self.layout.empty_root {
+            // `<XDFFORMAT ... />` becomes `<XDFFORMAT ...>` items `</XDFFORMAT>`
+            out.push(b'>');
+            items(&mut out, &format!("{newline}{indent}"));
+            out.extend_from_slice(format!("{newline}</{}>", self.layout.root).as_bytes());
+            (end, out)
+        } else if let Some(line_start) = self.line_start(end.start) {
+            // End tag on its own line, add the items as lines before it
+            for entry in added {
+                out.extend_from_slice(indent.as_bytes());
+                out.extend_from_slice(&item_xml(&entry.item, indent, newline));
+                out.extend_from_slice(newline.as_bytes());
+            }
+            (line_start..line_start, out)
+        } else {
+            items(&mut out, &format!("{newline}{indent}"));
+            out.extend_from_slice(newline.as_bytes());
+            (end.start..end.start, out)
+        }
+    }
+}
+
+/// Byte offsets of the parts of a document that the editor rewrites.
+#[derive(Debug, Clone, Default)]
+struct Layout {
+    /// Name of the root element as written
+    root: String,
+    /// Spans of the child elements of the root element
+    children: Vec<Range<usize>>,
+    /// Span of the root end tag, or of the `/>` closing an empty root element
+    end: Range<usize>,
+    empty_root: bool,
+}
+
+/// Kind of markup found by `Scanner::markup`.
+#[derive(Debug, PartialEq)]
+enum Markup {
+    Start {
+        empty: bool,
+    },
+    End,
+    /// Comment, processing instruction, CDATA section or document type declaration
+    Other,
+}
+
+/// Minimal XML scanner, only finds where markup starts and ends.
+/// The document has already been checked by the XML reader, so it does not validate anything.
+struct Scanner<'a> {
+    source: &'a [u8],
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    /// Moves past the next occurrence of `pattern`, or to the end of the input.
+    fn skip_past(&mut self, pattern: &[u8]) {
+        self.pos = self.source[self.pos..]
+            .windows(pattern.len())
+            .position(|w| w == pattern)
+            .map_or(self.source.len(), |i| self.pos + i + pattern.len());
+    }
+
+    /// Moves to the next `<`, returns false at the end of the input.
+    fn next_markup(&mut self) -> bool {
+        match self.source[self.pos..].iter().position(|b| *b == b'<') {
+            Some(i) => {
+                self.pos += i;
+                true
+            }
+            None => {
+                self.pos = self.source.len();
+                false
+            }
+        }
+    }
+
+    /// Consumes the markup starting at the current `<`.
+    fn markup(&mut self) -> Markup {
+        let rest = &self.source[self.pos..];
+        if rest.starts_with(b"<!--") {
+            self.skip_past(b"-->");
+        } else if rest.starts_with(b"<![CDATA[") {
+            self.skip_past(b"]]>");
+        } else if rest.starts_with(b"<?") {
+            self.skip_past(b"?>");
+        } else if rest.starts_with(b"<!") {
+            self.skip_tag();
+        } else {
+            let end = rest.starts_with(b"</");
+            self.skip_tag();
+            return if end {
+                Markup::End
+            } else {
+                Markup::Start {
+                    empty: self.source[..self.pos].ends_with(b"/>"),
+                }
+            };
+        }
+        Markup::Other
+    }
+
+    /// Moves past the `>` ending a tag, ignoring any inside quotes or a document type's internal subset.
+    fn skip_tag(&mut self) {
+        let mut quote = None;
+        let mut brackets = 0;
+        while let Some(&b) = self.source.get(self.pos) {
+            self.pos += 1;
+            match (quote, b) {
+                (Some(q), b) if b == q => quote = None,
+                (Some(_), _) => {}
+                (None, b'"' | b'\'') => quote = Some(b),
+                (None, b'[') => brackets += 1,
+                (None, b']') => brackets -= 1,
+                (None, b'>') if brackets <= 0 => return,
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Layout {
+    fn scan(source: &[u8]) -> Self {
+        let mut scanner = Scanner { source, pos: 0 };
+        let mut layout = Self::default();
+
+        // Prolog up to the root start tag
+        loop {
+            if !scanner.next_markup() {
+                return layout;
+            }
+            let start = scanner.pos;
+            if let Markup::Start { empty } = scanner.markup() {
+                layout.root = source[start + 1..scanner.pos]
+                    .iter()
+                    .take_while(|b| !b.is_ascii_whitespace() && !matches!(b, b'/' | b'>'))
+                    .map(|b| *b as char)
+                    .collect();
+                if empty {
+                    layout.empty_root = true;
+                    layout.end = scanner.pos - 2..scanner.pos;
+                    return layout;
+                }
+                break;
+            }
+        }
+
+        // Children of the root element
+        while scanner.next_markup() {
+            let start = scanner.pos;
+            match scanner.markup() {
+                Markup::Other => {}
+                Markup::End => {
+                    layout.end = start..scanner.pos;
+                    break;
+                }
+                Markup::Start { empty: true } => layout.children.push(start..scanner.pos),
+                Markup::Start { empty: false } => {
+                    let mut depth = 1;
+                    while depth > 0 && scanner.next_markup() {
+                        match scanner.markup() {
+                            Markup::Start { empty: false } => depth += 1,
+                            Markup::End => depth -= 1,
+                            _ => {}
+                        }
+                    }
+                    layout.children.push(start..scanner.pos);
+                }
+            }
+        }
+        layout
+    }
+}
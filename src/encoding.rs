@@ -0,0 +1,215 @@
+//! Character encoding handling for XDF files.
+//! TunerPro writes XDFs in the Windows ANSI code page (usually Windows-1252) without an encoding declaration,
+//! so descriptions containing characters such as `°`, `µ` or umlauts are not valid UTF-8.
+//! Input is transcoded to UTF-8 before it reaches the XML reader, an encoding declaration is only used to pick the encoding to transcode from.
+
+use std::io::{self, Read};
+
+/// Character encoding of an XDF file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Detect the encoding:
+    /// UTF-16 is recognised by its byte order mark or by null bytes around the first `<`,
+    /// otherwise an XML declaration naming one of the encodings below (e.g. `encoding="windows-1252"`) is followed.
+    /// Anything else is read as UTF-8, with any bytes that are not valid UTF-8 read as Windows-1252.
+    ///
+    /// Without a declaration this is a guess: Windows-1252 text that happens to be valid UTF-8,
+    /// such as `Ã¼` (bytes 0xC3 0xBC), is read as UTF-8 (`ü`). Set the encoding when it is known.
+    #[default]
+    Auto,
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Windows1252,
+    /// ISO-8859-1 (Latin-1), every byte maps directly to the Unicode code point of the same value
+    Iso8859_1,
+}
+
+/// Characters for bytes 0x80 to 0x9F in Windows-1252, the rest of the code page matches ISO-8859-1.
+/// Bytes that are undefined in Windows-1252 map to the matching C1 control character.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+fn windows_1252(byte: u8) -> char {
+    match byte {
+        0x80..=0x9F => WINDOWS_1252_HIGH[(byte - 0x80) as usize],
+        b => b as char,
+    }
+}
+
+/// Input read before picking an encoding, enough for an XML declaration.
+const DETECT_LIMIT: usize = 256;
+
+/// Picks an encoding from the first bytes of a file, see `Encoding::Auto`.
+fn detect(start: &[u8]) -> Encoding {
+    match start {
+        [0xFF, 0xFE, ..] | [b'<', 0, ..] => Encoding::Utf16Le,
+        [0xFE, 0xFF, ..] | [0, b'<', ..] => Encoding::Utf16Be,
+        _ => declared(start).unwrap_or(Encoding::Auto),
+    }
+}
+
+/// Encoding named by the XML declaration at the start of a file, `None` without a declaration or for names that are not supported.
+/// UTF-16 is never taken from the declaration, it is recognised from the bytes.
+pub(crate) fn declared(start: &[u8]) -> Option<Encoding> {
+    let start = start.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(start);
+    let declaration = start.strip_prefix(b"<?xml")?;
+    let end = declaration.windows(2).position(|w| w == b"?>")?;
+    let declaration = std::str::from_utf8(&declaration[..end]).ok()?;
+    let value = declaration.split_once("encoding")?.1.trim_start();
+    let value = value.strip_prefix('=')?.trim_start();
+    let quote = value.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+    let (name, _) = value[1..].split_once(quote)?;
+    match name.to_ascii_lowercase().as_str() {
+        "utf-8" | "utf8" => Some(Encoding::Utf8),
+        "windows-1252" | "cp1252" | "x-cp1252" => Some(Encoding::Windows1252),
+        "iso-8859-1" | "iso8859-1" | "latin1" | "latin-1" | "l1" => Some(Encoding::Iso8859_1),
+        _ => None,
+    }
+}
+
+/// Reader adapter that transcodes its input to UTF-8, dropping any byte order mark.
+pub struct Transcoder<R: Read> {
+    inner: R,
+    /// Requested encoding, `Auto` is resolved once the start of the input has been seen
+    encoding: Encoding,
+    detected: bool,
+    /// Input bytes that have not been decoded yet, e.g. the start of a multi byte sequence
+    pending: Vec<u8>,
+    /// Decoded UTF-8 waiting to be read
+    decoded: Vec<u8>,
+    read_pos: usize,
+    /// No output has been produced yet, so a leading byte order mark should be dropped
+    at_start: bool,
+    eof: bool,
+}
+
+impl<R: Read> Transcoder<R> {
+    pub fn new(inner: R, encoding: Encoding) -> Self {
+        Self {
+            inner,
+            encoding,
+            detected: encoding != Encoding::Auto,
+            pending: Vec::new(),
+            decoded: Vec::new(),
+            read_pos: 0,
+            at_start: true,
+            eof: false,
+        }
+    }
+
+    /// Reads more input and decodes as much of it as possible.
+    fn fill(&mut self) -> io::Result<()> {
+        let mut chunk = [0; 8192];
+        let read = self.inner.read(&mut chunk)?;
+        self.eof = read == 0;
+        self.pending.extend_from_slice(&chunk[..read]);
+
+        if !self.detected {
+            // The declaration, if any, ends at the first `>`
+            let waiting = self.pending.len() < DETECT_LIMIT && !self.pending.contains(&b'>');
+            if waiting && !self.eof {
+                return Ok(());
+            }
+            self.encoding = detect(&self.pending);
+            self.detected = true;
+        }
+
+        self.decoded.clear();
+        self.read_pos = 0;
+        let mut out = String::new();
+        let consumed = match self.encoding {
+            Encoding::Auto | Encoding::Utf8 => decode_utf8(&self.pending, self.eof, &mut out),
+            Encoding::Utf16Le => {
+                decode_utf16(&self.pending, self.eof, u16::from_le_bytes, &mut out)?
+            }
+            Encoding::Utf16Be => {
+                decode_utf16(&self.pending, self.eof, u16::from_be_bytes, &mut out)?
+            }
+            Encoding::Windows1252 => {
+                out.extend(self.pending.iter().map(|b| windows_1252(*b)));
+                self.pending.len()
+            }
+            Encoding::Iso8859_1 => {
+                out.extend(self.pending.iter().map(|b| *b as char));
+                self.pending.len()
+            }
+        };
+        self.pending.drain(..consumed);
+
+        let mut out = out.as_str();
+        if self.at_start && !out.is_empty() {
+            out = out.strip_prefix('\u{FEFF}').unwrap_or(out);
+            self.at_start = false;
+        }
+        self.decoded.extend_from_slice(out.as_bytes());
+        Ok(())
+    }
+}
+
+/// Decodes UTF-8, reading invalid bytes as Windows-1252. Returns the number of bytes consumed,
+/// an incomplete sequence at the end of the input is left for the next call unless `eof` is set.
+fn decode_utf8(mut input: &[u8], eof: bool, out: &mut String) -> usize {
+    let total = input.len();
+    loop {
+        match std::str::from_utf8(input) {
+            Ok(valid) => {
+                out.push_str(valid);
+                return total;
+            }
+            Err(e) => {
+                let (valid, rest) = input.split_at(e.valid_up_to());
+                out.push_str(std::str::from_utf8(valid).expect("checked by from_utf8"));
+                if e.error_len().is_none() && !eof {
+                    return total - rest.len();
+                }
+                out.push(windows_1252(rest[0]));
+                input = &rest[1..];
+            }
+        }
+    }
+}
+
+/// Decodes UTF-16 with the given byte order. Returns the number of bytes consumed,
+/// an incomplete code unit or surrogate pair at the end of the input is left for the next call unless `eof` is set.
+fn decode_utf16(
+    input: &[u8],
+    eof: bool,
+    unit: fn([u8; 2]) -> u16,
+    out: &mut String,
+) -> io::Result<usize> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid UTF-16");
+    let units: Vec<u16> = input.chunks_exact(2).map(|c| unit([c[0], c[1]])).collect();
+    let mut consumed = units.len();
+    // Keep a trailing high surrogate until its pair has been read
+    if !eof && matches!(units.last(), Some(0xD800..=0xDBFF)) {
+        consumed -= 1;
+    }
+    for c in char::decode_utf16(units[..consumed].iter().copied()) {
+        out.push(c.map_err(|_| invalid())?);
+    }
+    if eof && !input.len().is_multiple_of(2) {
+        return Err(invalid());
+    }
+    Ok(consumed * 2)
+}
+
+impl<R: Read> Read for Transcoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.read_pos >= self.decoded.len() {
+            if self.eof {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+        let available = &self.decoded[self.read_pos..];
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.read_pos += len;
+        Ok(len)
+    }
+}
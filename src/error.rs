@@ -1,20 +1,193 @@
-use xml::reader::XmlEvent;
+use std::fmt;
 
-use crate::data_types::XDFElement;
+use xml::{
+    common::{Position, TextPosition},
+    reader::XmlEvent,
+};
 
-#[derive(Debug, PartialEq)]
-pub enum Error {
+use crate::data_types::{RawElement, XDFElement};
+
+/// What went wrong while parsing, see `Error` for where it went wrong.
+#[derive(Debug)]
+pub enum ErrorKind {
+    /// A required attribute or element was not present
     MissingItem,
-    BadValue,
-    UnknownType,
+    /// A value could not be parsed, contains the offending text
+    BadValue(String),
+    /// Element name not known by the parser
+    UnknownType(String),
+    /// Document root is not an XDFFORMAT element, contains the name of the root element
+    UnexpectedRoot(String),
     UnexpectedElement(XDFElement),
     UnexpectedEvent(XmlEvent),
     LeftoverData,
     XmlError(xml::reader::Error),
+    /// Reading the input failed before it reached the XML reader, e.g. the file could not be opened
+    Io(std::io::Error),
+}
+
+impl PartialEq for ErrorKind {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::MissingItem, Self::MissingItem) | (Self::LeftoverData, Self::LeftoverData) => {
+                true
+            }
+            (Self::BadValue(a), Self::BadValue(b))
+            | (Self::UnknownType(a), Self::UnknownType(b))
+            | (Self::UnexpectedRoot(a), Self::UnexpectedRoot(b)) => a == b,
+            (Self::UnexpectedElement(a), Self::UnexpectedElement(b)) => a == b,
+            (Self::UnexpectedEvent(a), Self::UnexpectedEvent(b)) => a == b,
+            (Self::XmlError(a), Self::XmlError(b)) => a == b,
+            // io::Error has no equality, compare what can be compared
+            (Self::Io(a), Self::Io(b)) => a.kind() == b.kind() && a.to_string() == b.to_string(),
+            _ => false,
+        }
+    }
+}
+
+/// Parser error, locates the failure by source position, element path and attribute name where known.
+#[derive(Debug, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    /// Position (as reported by the XML reader) of the element that failed to parse
+    pub position: Option<TextPosition>,
+    /// Path from the document root to the failing element,
+    /// e.g. `XDFFORMAT/XDFTABLE[uid=0x6E81]/XDFAXIS[z]/EMBEDDEDDATA`
+    pub path: Option<String>,
+    /// Name of the attribute that failed to parse, if the failure was in an attribute
+    pub attribute: Option<String>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            position: None,
+            path: None,
+            attribute: None,
+        }
+    }
+
+    /// Sets the name of the attribute that caused the error
+    pub fn with_attribute(mut self, name: &str) -> Self {
+        self.attribute = Some(name.to_string());
+        self
+    }
+
+    /// Sets the position and path of the error, unless they were already set by a more deeply nested element.
+    pub(crate) fn locate(mut self, position: TextPosition, path: impl FnOnce() -> String) -> Self {
+        if self.position.is_none() {
+            self.position = Some(position);
+        }
+        if self.path.is_none() {
+            self.path = Some(path());
+        }
+        self
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(value: ErrorKind) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::new(ErrorKind::Io(value))
+    }
 }
 
 impl From<xml::reader::Error> for Error {
     fn from(value: xml::reader::Error) -> Self {
-        Self::XmlError(value)
+        let position = value.position();
+        Self {
+            position: Some(position),
+            ..Self::new(ErrorKind::XmlError(value))
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingItem => write!(f, "missing required item"),
+            Self::BadValue(v) => write!(f, "bad value `{v}`"),
+            Self::UnknownType(t) => write!(f, "unknown element `{t}`"),
+            Self::UnexpectedRoot(r) => write!(f, "expected XDFFORMAT root element, found `{r}`"),
+            Self::UnexpectedElement(e) => write!(f, "unexpected element {e:?}"),
+            Self::UnexpectedEvent(e) => write!(f, "unexpected XML event {e:?}"),
+            Self::LeftoverData => write!(f, "leftover data after document"),
+            Self::XmlError(e) => write!(f, "XML error: {}", e.msg()),
+            Self::Io(e) => write!(f, "IO error: {e}"),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(position) = self.position {
+            write!(f, "{position}: ")?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, "{path}: ")?;
+        }
+        write!(f, "{}", self.kind)?;
+        if let Some(attribute) = &self.attribute {
+            write!(f, " in attribute `{attribute}`")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::XmlError(e) => Some(e),
+            ErrorKind::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Something the parser did not understand but could continue past, see `ParseOptions`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WarningKind {
+    /// Element not known by the parser, kept as a raw node
+    UnknownElement(RawElement),
+    /// Attribute not read by the parser for its element
+    UnknownAttribute { name: String, value: String },
+    /// Known element in a place it is not valid, dropped
+    UnexpectedElement(XDFElement),
+}
+
+/// Non fatal parser diagnostic, located in the same way as `Error`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    /// Position (as reported by the XML reader) of the element the warning applies to
+    pub position: Option<TextPosition>,
+    /// Path from the document root to the element the warning applies to
+    pub path: String,
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownElement(e) => write!(f, "unknown element `{}`", e.name),
+            Self::UnknownAttribute { name, value } => {
+                write!(f, "unknown attribute `{name}=\"{value}\"`")
+            }
+            Self::UnexpectedElement(e) => write!(f, "unexpected element {e:?}"),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(position) = self.position {
+            write!(f, "{position}: ")?;
+        }
+        write!(f, "{}: {}", self.path, self.kind)
     }
 }
@@ -0,0 +1,156 @@
+//! Extracting part of a definition, for example to share only the ignition tables of a large XDF.
+//! Tables referenced by linked axes of the selected tables are always included so the result stays usable.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    ops::Range,
+};
+
+use regex::Regex;
+
+use crate::{
+    data_types::*,
+    ids::{items, link_target},
+    reader::XdfItem,
+};
+
+/// Items to extract from a definition.
+/// An item is selected when it matches every kind of criterion that was given, and any of the values given for that kind.
+/// A filter without criteria selects everything.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    categories: Vec<String>,
+    titles: Vec<Regex>,
+    addresses: Vec<Range<u32>>,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Selects members of the header category with this name.
+    pub fn category(mut self, name: impl Into<String>) -> Self {
+        self.categories.push(name.into());
+        self
+    }
+
+    /// Selects items whose title matches the pattern.
+    pub fn title(mut self, pattern: Regex) -> Self {
+        self.titles.push(pattern);
+        self
+    }
+
+    /// Selects items writing to any byte within the window.
+    pub fn addresses(mut self, window: Range<u32>) -> Self {
+        self.addresses.push(window);
+        self
+    }
+
+    fn matches(&self, item: &XdfItem, categories: &BTreeSet<u32>) -> bool {
+        let category = || {
+            item.catmem()
+                .iter()
+                .any(|m| m.category.is_some_and(|c| categories.contains(&c)))
+        };
+        let title = || {
+            let title = item.title().unwrap_or_default();
+            self.titles.iter().any(|t| t.is_match(title))
+        };
+        let address = || {
+            item.byte_ranges().iter().any(|r| {
+                self.addresses
+                    .iter()
+                    .any(|w| r.start < u64::from(w.end) && u64::from(w.start) < r.end)
+            })
+        };
+        (self.categories.is_empty() || category())
+            && (self.titles.is_empty() || title())
+            && (self.addresses.is_empty() || address())
+    }
+}
+
+impl XDFFormat {
+    /// Copy of the definition holding only the items selected by `filter` and the tables their axes link to.
+    /// Header categories no longer in use are dropped and the rest numbered from 0,
+    /// memberships of categories not declared in the header are dropped.
+    pub fn extract(&self, filter: &Filter) -> XDFFormat {
+        let declared = self.header.iter().flat_map(|h| &h.category);
+        let categories: BTreeSet<u32> = declared
+            .filter(|c| {
+                c.name
+                    .as_ref()
+                    .is_some_and(|n| filter.categories.contains(n))
+            })
+            .filter_map(|c| c.index)
+            .collect();
+
+        let all: Vec<XdfItem> = items(self).into_iter().map(|(_, item)| item).collect();
+        let mut selected: Vec<bool> = all
+            .iter()
+            .map(|item| filter.matches(item, &categories))
+            .collect();
+
+        // Follow linked axes until no more tables are pulled in
+        let mut pending: Vec<usize> = (0..all.len()).filter(|i| selected[*i]).collect();
+        while let Some(i) = pending.pop() {
+            let XdfItem::Table(table) = &all[i] else {
+                continue;
+            };
+            for target in table.axis.iter().filter_map(link_target) {
+                let linked = all
+                    .iter()
+                    .position(|item| matches!(item, XdfItem::Table(t) if t.uid == Some(target)));
+                if let Some(linked) = linked.filter(|l| !selected[*l]) {
+                    selected[linked] = true;
+                    pending.push(linked);
+                }
+            }
+        }
+
+        let mut extracted = XDFFormat {
+            version: self.version.clone(),
+            header: self.header.clone(),
+            ..Default::default()
+        };
+        let kept = all
+            .into_iter()
+            .zip(selected)
+            .filter_map(|(item, selected)| selected.then_some(item));
+        extracted.replace_items(kept);
+        extracted.renumber_categories();
+        extracted
+    }
+
+    /// Drops header categories without members and numbers the rest from 0, in header order.
+    fn renumber_categories(&mut self) {
+        let mut items: Vec<XdfItem> = items(self).into_iter().map(|(_, item)| item).collect();
+        let Some(header) = &mut self.header else {
+            return;
+        };
+        let used: BTreeSet<u32> = items
+            .iter()
+            .flat_map(XdfItem::catmem)
+            .filter_map(|m| m.category)
+            .collect();
+        header
+            .category
+            .retain(|c| c.index.is_some_and(|i| used.contains(&i)));
+        let mut mapping = HashMap::new();
+        for (category, new) in header.category.iter_mut().zip(0..) {
+            if let Some(old) = category.index {
+                mapping.entry(old).or_insert(new);
+            }
+            category.index = Some(new);
+        }
+
+        for catmem in items.iter_mut().filter_map(XdfItem::catmem_mut) {
+            catmem.retain(|m| m.category.is_some_and(|c| mapping.contains_key(&c)));
+            for (membership, index) in catmem.iter_mut().zip(0..) {
+                membership.index = Some(index);
+                membership.category = membership.category.map(|c| mapping[&c]);
+            }
+        }
+        self.replace_items(items);
+    }
+}
@@ -0,0 +1,297 @@
+//! Management of item uniqueids.
+//! TunerPro identifies tables, constants, patches, flags and checksums by their `uniqueid`,
+//! and linked axes refer to other tables through `embedinfo linkobjid`.
+//! Nothing enforces that ids are unique, hand edited and merged files often contain duplicates, which silently breaks linked axes.
+//!
+//! Axis uniqueids are always `0x0` in files written by TunerPro, they are not ids and are left alone.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+};
+
+use crate::{data_types::*, reader::XdfItem};
+
+/// Item of an `XDFFormat`, by type and position in the list for that type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ItemRef {
+    Table(usize),
+    Constant(usize),
+    Patch(usize),
+    Flag(usize),
+    Checksum(usize),
+}
+
+/// Axis that takes its values from another table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Link {
+    /// Position of the table owning the axis in `XDFFormat::tables`
+    pub table: usize,
+    /// Position of the axis in `XDFTable::axis`
+    pub axis: usize,
+    /// `linkobjid` of the axis
+    pub target: u32,
+}
+
+/// Id given to an item by `XDFFormat::make_ids_unique`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdChange {
+    pub item: ItemRef,
+    pub old: Option<u32>,
+    pub new: u32,
+}
+
+/// Ids from `first` run out before every item has one, see `XDFFormat::renumber_ids`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdOverflow {
+    pub first: u32,
+    /// Number of items to be numbered
+    pub items: usize,
+}
+
+impl fmt::Display for IdOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} items numbered from 0x{:X} run past the largest id",
+            self.items, self.first
+        )
+    }
+}
+
+impl std::error::Error for IdOverflow {}
+
+/// Uniqueids in use by a document, see `XDFFormat::ids`.
+/// Changes to the document after the registry was created are not reflected in it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IdRegistry {
+    /// Items using each id, in document order
+    ids: BTreeMap<u32, Vec<ItemRef>>,
+    links: Vec<Link>,
+}
+
+impl IdRegistry {
+    pub fn new(format: &XDFFormat) -> Self {
+        let mut registry = Self::default();
+        for (item, uid) in uids(format) {
+            if let Some(uid) = uid {
+                registry.ids.entry(uid).or_default().push(item);
+            }
+        }
+        for (table, t) in format.tables.iter().enumerate() {
+            for (axis, a) in t.axis.iter().enumerate() {
+                if let Some(target) = link_target(a) {
+                    registry.links.push(Link {
+                        table,
+                        axis,
+                        target,
+                    });
+                }
+            }
+        }
+        registry
+    }
+
+    pub fn contains(&self, uid: u32) -> bool {
+        self.ids.contains_key(&uid)
+    }
+
+    /// Items using an id, in document order.
+    pub fn items(&self, uid: u32) -> &[ItemRef] {
+        self.ids.get(&uid).map_or(&[], Vec::as_slice)
+    }
+
+    /// Ids used by more than one item, with the items using them.
+    pub fn duplicates(&self) -> impl Iterator<Item = (u32, &[ItemRef])> {
+        self.ids
+            .iter()
+            .filter(|(_, items)| items.len() > 1)
+            .map(|(uid, items)| (*uid, items.as_slice()))
+    }
+
+    /// Every linked axis of the document.
+    pub fn links(&self) -> &[Link] {
+        &self.links
+    }
+
+    /// Linked axes whose target id is not used by any item.
+    pub fn dangling_links(&self) -> impl Iterator<Item = &Link> {
+        self.links.iter().filter(|l| !self.contains(l.target))
+    }
+
+    /// Target ids of the dangling links, ids that must not be handed out to keep those links dangling.
+    fn dangling_targets(&self) -> HashSet<u32> {
+        self.dangling_links().map(|l| l.target).collect()
+    }
+
+    /// Marks an id as in use, returns false if it already was.
+    pub fn reserve(&mut self, uid: u32) -> bool {
+        if self.contains(uid) {
+            return false;
+        }
+        self.ids.insert(uid, Vec::new());
+        true
+    }
+
+    /// Reserves an id that is not in use, one above the highest id in use where possible.
+    /// Ids handed out are remembered, so repeated calls return different ids.
+    pub fn allocate(&mut self) -> u32 {
+        let uid = match self.ids.last_key_value() {
+            None => 1,
+            Some((&max, _)) if max < u32::MAX => max + 1,
+            // Highest id is taken, use the first gap instead
+            Some(_) => (1..)
+                .find(|uid| !self.contains(*uid))
+                .expect("ran out of ids"),
+        };
+        self.ids.insert(uid, Vec::new());
+        uid
+    }
+}
+
+/// Pairs the items of a definition other than the header with their `ItemRef`, in document order.
+/// Each list is borrowed with `$iter` (`iter` or `iter_mut`) and `$value` gives what is paired with an item.
+macro_rules! each_item {
+    ($format:expr, $iter:ident, |$item:ident| $value:expr) => {{
+        let format = $format;
+        (format.tables.$iter().enumerate())
+            .map(|(i, $item)| (ItemRef::Table(i), $value))
+            .chain(
+                (format.constants.$iter().enumerate())
+                    .map(|(i, $item)| (ItemRef::Constant(i), $value)),
+            )
+            .chain(
+                (format.patches.$iter().enumerate()).map(|(i, $item)| (ItemRef::Patch(i), $value)),
+            )
+            .chain((format.flags.$iter().enumerate()).map(|(i, $item)| (ItemRef::Flag(i), $value)))
+            .chain(
+                (format.checksums.$iter().enumerate())
+                    .map(|(i, $item)| (ItemRef::Checksum(i), $value)),
+            )
+    }};
+}
+
+/// Items of a definition other than the header, in document order.
+pub(crate) fn items(format: &XDFFormat) -> Vec<(ItemRef, XdfItem)> {
+    each_item!(format, iter, |item| XdfItem::from(item.clone())).collect()
+}
+
+/// Ids of every item in document order.
+fn uids(format: &XDFFormat) -> impl Iterator<Item = (ItemRef, Option<u32>)> + '_ {
+    each_item!(format, iter, |item| item.uid)
+}
+
+/// Id fields of every item in document order.
+fn uids_mut(format: &mut XDFFormat) -> impl Iterator<Item = (ItemRef, &mut Option<u32>)> {
+    each_item!(format, iter_mut, |item| &mut item.uid)
+}
+
+/// `linkobjid` of a linked axis (`embedinfo type="3"`).
+pub(crate) fn link_target(axis: &XDFAxis) -> Option<u32> {
+    match axis.embedinfo {
+        Some(EmbedInfo {
+            etype: Some(3),
+            linkobjid,
+        }) => linkobjid,
+        _ => None,
+    }
+}
+
+/// Points the linked axes of a table at new ids, links to ids not in `mapping` are left as they are.
+pub(crate) fn remap_links(table: &mut XDFTable, mapping: &HashMap<u32, u32>) {
+    for axis in &mut table.axis {
+        if let Some(new) = link_target(axis).and_then(|old| mapping.get(&old)) {
+            if let Some(embedinfo) = &mut axis.embedinfo {
+                embedinfo.linkobjid = Some(*new);
+            }
+        }
+    }
+}
+
+impl XdfItem {
+    pub(crate) fn set_uid(&mut self, uid: Option<u32>) {
+        match self {
+            Self::Header(_) => {}
+            Self::Table(v) => v.uid = uid,
+            Self::Constant(v) => v.uid = uid,
+            Self::Patch(v) => v.uid = uid,
+            Self::Flag(v) => v.uid = uid,
+            Self::Checksum(v) => v.uid = uid,
+        }
+    }
+}
+
+impl XDFFormat {
+    /// Registry of the ids currently in use.
+    pub fn ids(&self) -> IdRegistry {
+        IdRegistry::new(self)
+    }
+
+    /// Replaces ids according to `mapping` (old id to new id), both on items and in the `linkobjid` of linked axes.
+    /// Ids not in the mapping are left as they are.
+    pub fn remap_ids(&mut self, mapping: &HashMap<u32, u32>) {
+        for (_, uid) in uids_mut(self) {
+            if let Some(new) = uid.and_then(|old| mapping.get(&old)) {
+                *uid = Some(*new);
+            }
+        }
+        self.remap_links(mapping);
+    }
+
+    fn remap_links(&mut self, mapping: &HashMap<u32, u32>) {
+        for table in &mut self.tables {
+            remap_links(table, mapping);
+        }
+    }
+
+    /// Gives fresh ids to items without one, and to every item but the first using a duplicated id.
+    /// Links keep pointing at the first item with their target id, ids that dangling links point at are not handed out.
+    /// Returns the ids that were changed.
+    pub fn make_ids_unique(&mut self) -> Vec<IdChange> {
+        let mut registry = self.ids();
+        for target in registry.dangling_targets() {
+            registry.reserve(target);
+        }
+        let mut seen = HashSet::new();
+        let mut changes = Vec::new();
+        for (item, uid) in uids_mut(self) {
+            let unique = matches!(*uid, Some(old) if seen.insert(old));
+            if !unique {
+                let new = registry.allocate();
+                changes.push(IdChange {
+                    item,
+                    old: *uid,
+                    new,
+                });
+                *uid = Some(new);
+            }
+        }
+        changes
+    }
+
+    /// Numbers every item consecutively from `first` in document order (tables, constants, patches, flags, checksums),
+    /// rewriting links to point at the new id of their target.
+    /// Ids that dangling links point at are skipped, so those links do not end up pointing at a renumbered item.
+    /// Returns the new id of each old id, a duplicated id maps to the new id of its first item.
+    /// Fails without changing anything if the last item would need an id above `u32::MAX`.
+    pub fn renumber_ids(&mut self, first: u32) -> Result<HashMap<u32, u32>, IdOverflow> {
+        let dangling = self.ids().dangling_targets();
+        let items = uids(self).count();
+        let ids: Vec<u32> = (first..=u32::MAX)
+            .filter(|id| !dangling.contains(id))
+            .take(items)
+            .collect();
+        if ids.len() < items {
+            return Err(IdOverflow { first, items });
+        }
+        let mut mapping = HashMap::new();
+        for ((_, uid), new) in uids_mut(self).zip(ids) {
+            if let Some(old) = *uid {
+                mapping.entry(old).or_insert(new);
+            }
+            *uid = Some(new);
+        }
+        self.remap_links(&mapping);
+        Ok(mapping)
+    }
+}
@@ -1,27 +1,67 @@
-#![feature(if_let_guard)]
+// Errors carry the element or event that was not expected by value, as callers match on them
+#![allow(clippy::result_large_err, clippy::large_enum_variant)]
 
 use std::io::{BufReader, Read};
 
 use xml::{EventReader, ParserConfig};
 
+pub mod bin;
+pub mod builder;
+pub mod cache;
+pub mod constant;
 pub mod data_types;
+mod decode;
+pub mod document;
+pub mod encoding;
 pub mod error;
+pub mod extract;
+pub mod ids;
+pub mod math;
+pub mod merge;
 pub mod parser;
+pub mod reader;
+pub mod rebase;
+pub mod table;
+pub mod writer;
 
-pub fn parse_buffer<R: Read>(
+/// Creates an XML reader configured the way the XDF parser expects.
+/// Input is transcoded to UTF-8 first, any encoding declaration in the document only picks the encoding to transcode from.
+fn event_reader<R: Read>(
     from: R,
-) -> Result<Result<data_types::XDFElement, error::Error>, std::io::Error> {
-    let file = BufReader::new(from); // Buffering is important for performance
+    encoding: encoding::Encoding,
+) -> EventReader<BufReader<encoding::Transcoder<R>>> {
+    // Buffering is important for performance
+    let file = BufReader::new(encoding::Transcoder::new(from, encoding));
 
-    let mut parser = EventReader::new_with_config(
+    EventReader::new_with_config(
         file,
         ParserConfig::new()
             .ignore_comments(true)
             .trim_whitespace(true)
             .whitespace_to_characters(true)
             .cdata_to_characters(true)
-            .coalesce_characters(true),
-    );
+            .coalesce_characters(true)
+            .override_encoding(Some(xml::Encoding::Utf8))
+            .ignore_invalid_encoding_declarations(true),
+    )
+}
 
-    Ok(data_types::XDFElement::from_xml(&mut parser))
+pub fn parse_buffer<R: Read>(
+    from: R,
+) -> Result<Result<data_types::XDFElement, error::Error>, std::io::Error> {
+    Ok(data_types::XDFElement::from_xml(&mut event_reader(
+        from,
+        encoding::Encoding::default(),
+    )))
+}
+
+/// Parses an XDF document, unknown content is handled according to `options` and reported in the returned warnings.
+pub fn parse_buffer_with_options<R: Read>(
+    from: R,
+    options: parser::ParseOptions,
+) -> Result<(data_types::XDFElement, Vec<error::Warning>), error::Error> {
+    data_types::XDFElement::from_xml_with_options(
+        &mut event_reader(from, options.encoding),
+        options,
+    )
 }
@@ -0,0 +1,290 @@
+//! Evaluation of `MATH` equations, converting between values stored in a bin and the values shown to the user.
+//! Equations are arithmetic on numbers and the stored value `X` (any case, or a name listed in the `VAR`s):
+//! `+ - * / ^`, unary minus and parentheses, e.g. `(X*0.75)-48` or `X * .250000`.
+//! TunerPro functions and references to other items are not supported.
+//!
+//! Equations using `X` exactly once are inverted step by step to find the stored value for a result,
+//! which covers the scale and offset conversions of almost every definition.
+
+use std::fmt;
+
+use crate::data_types::Math;
+
+/// Reason a `MATH` equation could not be used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MathError {
+    /// Equation is not valid, contains the byte offset of the problem in the equation
+    Syntax(usize),
+    /// Equation uses a variable or function other than the stored value
+    UnknownName(String),
+    /// Stored value cannot be worked out from a result, as the equation does not use it exactly once
+    NotInvertible,
+}
+
+impl fmt::Display for MathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax(at) => write!(f, "invalid equation at offset {at}"),
+            Self::UnknownName(name) => write!(f, "unsupported name `{name}` in equation"),
+            Self::NotInvertible => write!(f, "equation cannot be inverted"),
+        }
+    }
+}
+
+impl std::error::Error for MathError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+impl Op {
+    fn apply(self, a: f64, b: f64) -> f64 {
+        match self {
+            Op::Add => a + b,
+            Op::Sub => a - b,
+            Op::Mul => a * b,
+            Op::Div => a / b,
+            Op::Pow => a.powf(b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    X,
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, x: f64) -> f64 {
+        match self {
+            Expr::Number(n) => *n,
+            Expr::X => x,
+            Expr::Neg(e) => -e.eval(x),
+            Expr::Binary(op, a, b) => op.apply(a.eval(x), b.eval(x)),
+        }
+    }
+
+    fn uses_x(&self) -> usize {
+        match self {
+            Expr::Number(_) => 0,
+            Expr::X => 1,
+            Expr::Neg(e) => e.uses_x(),
+            Expr::Binary(_, a, b) => a.uses_x() + b.uses_x(),
+        }
+    }
+
+    /// Value of `X` for which the expression gives `y`, the expression must use `X` exactly once.
+    fn solve(&self, y: f64) -> f64 {
+        match self {
+            Expr::X => y,
+            Expr::Neg(e) => e.solve(-y),
+            Expr::Binary(op, a, b) if a.uses_x() == 1 => {
+                let c = b.eval(0.0);
+                a.solve(match op {
+                    Op::Add => y - c,
+                    Op::Sub => y + c,
+                    Op::Mul => y / c,
+                    Op::Div => y * c,
+                    Op::Pow => y.powf(1.0 / c),
+                })
+            }
+            Expr::Binary(op, a, b) => {
+                let c = a.eval(0.0);
+                b.solve(match op {
+                    Op::Add => y - c,
+                    Op::Sub => c - y,
+                    Op::Mul => y / c,
+                    Op::Div => c / y,
+                    Op::Pow => y.ln() / c.ln(),
+                })
+            }
+            Expr::Number(_) => f64::NAN,
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    vars: &'a [String],
+}
+
+impl Parser<'_> {
+    fn peek(&mut self) -> Option<char> {
+        let rest = &self.input[self.pos..];
+        let trimmed = rest.trim_start();
+        self.pos += rest.len() - trimmed.len();
+        trimmed.chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Sums and differences, the loosest binding level.
+    fn expr(&mut self) -> Result<Expr, MathError> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = if self.eat('+') {
+                Op::Add
+            } else if self.eat('-') {
+                Op::Sub
+            } else {
+                return Ok(lhs);
+            };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, MathError> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                Op::Mul
+            } else if self.eat('/') {
+                Op::Div
+            } else {
+                return Ok(lhs);
+            };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, MathError> {
+        if self.eat('-') {
+            Ok(Expr::Neg(Box::new(self.unary()?)))
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    /// Powers bind tighter than a leading minus and are right associative, `-2^2^3` is `-(2^(2^3))`.
+    fn power(&mut self) -> Result<Expr, MathError> {
+        let base = self.atom()?;
+        if self.eat('^') {
+            Ok(Expr::Binary(
+                Op::Pow,
+                Box::new(base),
+                Box::new(self.unary()?),
+            ))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr, MathError> {
+        let start = self.pos;
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let inner = self.expr()?;
+                if !self.eat(')') {
+                    return Err(MathError::Syntax(self.pos));
+                }
+                Ok(inner)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.pos;
+                let len = self.input[start..]
+                    .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                    .unwrap_or(self.input.len() - start);
+                self.pos += len;
+                self.input[start..self.pos]
+                    .parse()
+                    .map(Expr::Number)
+                    .map_err(|_| MathError::Syntax(start))
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let start = self.pos;
+                let len = self.input[start..]
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(self.input.len() - start);
+                self.pos += len;
+                let name = &self.input[start..self.pos];
+                let is_x = name.eq_ignore_ascii_case("x")
+                    || self.vars.iter().any(|v| v.eq_ignore_ascii_case(name));
+                if is_x {
+                    Ok(Expr::X)
+                } else {
+                    Err(MathError::UnknownName(name.to_string()))
+                }
+            }
+            _ => Err(MathError::Syntax(start.max(self.pos))),
+        }
+    }
+}
+
+/// Parsed `MATH` equation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Equation {
+    expr: Expr,
+}
+
+impl Equation {
+    /// Parses an equation, `vars` are further names for the stored value besides `X`.
+    pub fn parse(expression: &str, vars: &[String]) -> Result<Self, MathError> {
+        let mut parser = Parser {
+            input: expression,
+            pos: 0,
+            vars,
+        };
+        let expr = parser.expr()?;
+        if parser.peek().is_some() {
+            return Err(MathError::Syntax(parser.pos));
+        }
+        Ok(Self { expr })
+    }
+
+    /// Equation returning the stored value unchanged.
+    pub fn identity() -> Self {
+        Self { expr: Expr::X }
+    }
+
+    /// Value shown to the user for the stored value `x`.
+    pub fn eval(&self, x: f64) -> f64 {
+        self.expr.eval(x)
+    }
+
+    /// Stored value for which the equation gives `y`, not rounded to what the bin can hold.
+    pub fn invert(&self, y: f64) -> Result<f64, MathError> {
+        if self.expr.uses_x() != 1 {
+            return Err(MathError::NotInvertible);
+        }
+        let x = self.expr.solve(y);
+        if x.is_finite() {
+            Ok(x)
+        } else {
+            Err(MathError::NotInvertible)
+        }
+    }
+}
+
+impl Math {
+    /// Parses the expression, a missing expression leaves values unchanged.
+    pub fn equation(&self) -> Result<Equation, MathError> {
+        match &self.expression {
+            Some(expression) => Equation::parse(expression, &self.vars),
+            None => Ok(Equation::identity()),
+        }
+    }
+}
+
+/// Equation of an item's optional `MATH`.
+pub(crate) fn equation(math: Option<&Math>) -> Result<Equation, MathError> {
+    math.map_or_else(|| Ok(Equation::identity()), Math::equation)
+}
@@ -0,0 +1,437 @@
+//! Combining two XDF definitions, for example a community XDF with in-house additions.
+//! Items of the other definition are matched to ours by title, then tables, constants and patches by address.
+//! Identical items are skipped, differing ones are reported as conflicts and resolved according to a `MergePolicy`.
+//!
+//! Header categories are merged by name and the `CategoryMem` of incoming items renumbered to match.
+//! Incoming items keep their uniqueid where it is free and are given a fresh one otherwise,
+//! linked axes of incoming tables are rewritten to follow.
+
+use std::{collections::HashMap, mem::discriminant, ops::Range};
+
+use crate::{
+    data_types::*,
+    ids::{items, remap_links, IdRegistry, ItemRef},
+    reader::XdfItem,
+};
+
+/// What to do with an incoming item that conflicts with one of ours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Resolution {
+    /// Keep our item and drop theirs
+    #[default]
+    KeepOurs,
+    /// Replace our item with theirs, theirs takes over our uniqueid so links to it keep working
+    TakeTheirs,
+    /// Keep both items
+    KeepBoth,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// Same title, different address
+    Address,
+    /// Same address, different math
+    Math,
+    /// Same address and math, but other fields such as the title or description differ
+    Changed,
+    /// Not matched to any of our items, but writes to bytes used by one of them
+    Overlap,
+}
+
+/// Resolution to apply to each kind of conflict, the default keeps our items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MergePolicy {
+    pub address: Resolution,
+    pub math: Resolution,
+    pub changed: Resolution,
+    pub overlap: Resolution,
+}
+
+impl MergePolicy {
+    /// Same resolution for every kind of conflict.
+    pub fn all(resolution: Resolution) -> Self {
+        Self {
+            address: resolution,
+            math: resolution,
+            changed: resolution,
+            overlap: resolution,
+        }
+    }
+
+    pub fn resolution(&self, kind: ConflictKind) -> Resolution {
+        match kind {
+            ConflictKind::Address => self.address,
+            ConflictKind::Math => self.math,
+            ConflictKind::Changed => self.changed,
+            ConflictKind::Overlap => self.overlap,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conflict {
+    pub kind: ConflictKind,
+    /// Our item, by position before the merge
+    pub ours: ItemRef,
+    /// Their item, by position in the other definition
+    pub theirs: ItemRef,
+    pub resolution: Resolution,
+}
+
+/// Outcome of `XDFFormat::merge`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergeReport {
+    pub conflicts: Vec<Conflict>,
+    /// Their items added alongside ours, by position in the other definition
+    pub added: Vec<ItemRef>,
+    /// Id in the merged definition of each of their ids.
+    /// Dropped items that were not matched to one of ours map to an unused id, so links to them dangle rather than pointing at an unrelated item.
+    pub ids: HashMap<u32, u32>,
+}
+
+impl XdfItem {
+    pub(crate) fn catmem_mut(&mut self) -> Option<&mut Vec<CategoryMem>> {
+        match self {
+            Self::Header(_) => None,
+            Self::Table(v) => Some(&mut v.catmem),
+            Self::Constant(v) => Some(&mut v.catmem),
+            Self::Patch(v) => Some(&mut v.catmem),
+            Self::Flag(v) => Some(&mut v.catmem),
+            Self::Checksum(v) => Some(&mut v.catmem),
+        }
+    }
+
+    /// Address the item is stored at: the z axis data of tables, the first entry of patches and the result of checksums.
+    pub fn address(&self) -> Option<u32> {
+        match self {
+            Self::Header(_) => None,
+            Self::Table(v) => v.axis_by_id("z")?.embeddeddata.as_ref()?.mmedaddress,
+            Self::Constant(v) => v.embedded_data.as_ref()?.mmedaddress,
+            Self::Patch(v) => v.entries.first()?.address,
+            Self::Flag(v) => v.embedded_data.as_ref()?.mmedaddress,
+            Self::Checksum(v) => v.regions.first()?.storeaddress,
+        }
+    }
+
+    /// Bytes of the bin written by the item. Axis data of tables is not included, nor are the summed ranges of checksums.
+    pub fn byte_ranges(&self) -> Vec<Range<u64>> {
+        let data = |d: &Option<EmbeddedData>| d.as_ref().and_then(EmbeddedData::byte_range);
+        match self {
+            Self::Header(_) => Vec::new(),
+            Self::Table(v) => v
+                .axis_by_id("z")
+                .and_then(|z| data(&z.embeddeddata))
+                .into_iter()
+                .collect(),
+            Self::Constant(v) => data(&v.embedded_data).into_iter().collect(),
+            Self::Patch(v) => v
+                .entries
+                .iter()
+                .filter_map(|e| {
+                    let start = u64::from(e.address?);
+                    Some(start..start + u64::from(e.datasize?))
+                })
+                .collect(),
+            Self::Flag(v) => data(&v.embedded_data).into_iter().collect(),
+            Self::Checksum(v) => v
+                .regions
+                .iter()
+                .filter_map(|r| {
+                    let start = u64::from(r.storeaddress?);
+                    Some(start..start + u64::from(r.datasizebits.unwrap_or(8).div_ceil(8)))
+                })
+                .collect(),
+        }
+    }
+
+    /// Conversion applied to the stored data: the z axis math of tables and the math of constants.
+    pub fn math(&self) -> Option<&Math> {
+        match self {
+            Self::Table(v) => v.axis_by_id("z")?.math.as_ref(),
+            Self::Constant(v) => v.math.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// Items that own the bytes they write, flags share bytes by design and checksums are only matched by title.
+fn has_storage(item: &XdfItem) -> bool {
+    matches!(
+        item,
+        XdfItem::Table(_) | XdfItem::Constant(_) | XdfItem::Patch(_)
+    )
+}
+
+fn overlaps(a: &XdfItem, b: &XdfItem) -> bool {
+    has_storage(a)
+        && has_storage(b)
+        && a.byte_ranges().iter().any(|a| {
+            b.byte_ranges()
+                .iter()
+                .any(|b| a.start < b.end && b.start < a.end)
+        })
+}
+
+/// Our item corresponding to an incoming one, by title and failing that by address.
+fn find_match(ours: &[(ItemRef, Option<XdfItem>)], item: &XdfItem) -> Option<usize> {
+    let candidates = || {
+        ours.iter()
+            .enumerate()
+            .filter_map(|(i, (_, o))| Some((i, o.as_ref()?)))
+            .filter(|(_, o)| discriminant(*o) == discriminant(item))
+    };
+    let title = item.title().filter(|t| !t.is_empty());
+    if let Some(found) = title.and_then(|t| candidates().find(|(_, o)| o.title() == Some(t))) {
+        return Some(found.0);
+    }
+    let address = item.address().filter(|_| has_storage(item))?;
+    candidates()
+        .find(|(_, o)| o.address() == Some(address))
+        .map(|(i, _)| i)
+}
+
+/// How an incoming item differs from the one it was matched to, `None` if they are the same.
+fn compare(ours: &XdfItem, theirs: &XdfItem, links: &HashMap<u32, u32>) -> Option<ConflictKind> {
+    let mut theirs = theirs.clone();
+    theirs.set_uid(ours.uid());
+    if let XdfItem::Table(table) = &mut theirs {
+        remap_links(table, links);
+    }
+    if *ours == theirs {
+        None
+    } else if ours.address() != theirs.address() {
+        Some(ConflictKind::Address)
+    } else if ours.math() != theirs.math() {
+        Some(ConflictKind::Math)
+    } else {
+        Some(ConflictKind::Changed)
+    }
+}
+
+/// Merge state, our items are replaced by `None` when removed so positions stay valid.
+struct Merger {
+    ours: Vec<(ItemRef, Option<XdfItem>)>,
+    /// Positions in `ours` of incoming items
+    incoming: Vec<usize>,
+    registry: IdRegistry,
+    report: MergeReport,
+}
+
+impl Merger {
+    fn uid(&self, index: usize) -> Option<u32> {
+        self.ours[index].1.as_ref()?.uid()
+    }
+
+    fn map(&mut self, from: Option<u32>, to: Option<u32>) {
+        if let (Some(from), Some(to)) = (from, to) {
+            self.report.ids.entry(from).or_insert(to);
+        }
+    }
+
+    /// Adds an incoming item, keeping its id if it is free.
+    fn add(&mut self, reference: ItemRef, mut item: XdfItem) {
+        let uid = match item.uid() {
+            Some(uid) if self.registry.reserve(uid) => uid,
+            _ => self.registry.allocate(),
+        };
+        self.map(item.uid(), Some(uid));
+        item.set_uid(Some(uid));
+        self.incoming.push(self.ours.len());
+        self.ours.push((reference, Some(item)));
+        self.report.added.push(reference);
+    }
+
+    /// Puts an incoming item in place of ours, keeping our id.
+    fn replace(&mut self, index: usize, mut item: XdfItem) {
+        let uid = self.uid(index);
+        self.map(item.uid(), uid);
+        item.set_uid(uid);
+        self.ours[index].1 = Some(item);
+        self.incoming.push(index);
+    }
+
+    /// Drops an incoming item that was not matched to one of ours.
+    fn drop_unmatched(&mut self, item: &XdfItem) {
+        if item.uid().is_some() {
+            let uid = self.registry.allocate();
+            self.map(item.uid(), Some(uid));
+        }
+    }
+
+    fn conflict(&mut self, kind: ConflictKind, ours: usize, theirs: ItemRef, policy: MergePolicy) {
+        self.report.conflicts.push(Conflict {
+            kind,
+            ours: self.ours[ours].0,
+            theirs,
+            resolution: policy.resolution(kind),
+        });
+    }
+
+    fn merge_matched(
+        &mut self,
+        index: usize,
+        reference: ItemRef,
+        item: XdfItem,
+        links: &HashMap<u32, u32>,
+        policy: MergePolicy,
+    ) {
+        let Some(ours) = &self.ours[index].1 else {
+            // Our item was removed in favour of an earlier overlapping item
+            return self.add(reference, item);
+        };
+        let Some(kind) = compare(ours, &item, links) else {
+            let uid = ours.uid();
+            return self.map(item.uid(), uid);
+        };
+        self.conflict(kind, index, reference, policy);
+        match policy.resolution(kind) {
+            Resolution::KeepOurs => {
+                let uid = self.uid(index);
+                self.map(item.uid(), uid);
+            }
+            Resolution::TakeTheirs => self.replace(index, item),
+            Resolution::KeepBoth => self.add(reference, item),
+        }
+    }
+
+    fn merge_unmatched(
+        &mut self,
+        original: usize,
+        reference: ItemRef,
+        item: XdfItem,
+        policy: MergePolicy,
+    ) {
+        let overlapping: Vec<usize> = (0..original)
+            .filter(|i| matches!(&self.ours[*i].1, Some(o) if overlaps(o, &item)))
+            .collect();
+        for i in &overlapping {
+            self.conflict(ConflictKind::Overlap, *i, reference, policy);
+        }
+        if overlapping.is_empty() {
+            return self.add(reference, item);
+        }
+        match policy.overlap {
+            Resolution::KeepOurs => self.drop_unmatched(&item),
+            Resolution::TakeTheirs => {
+                // Links to our items follow the replacement when it is of the same type
+                let same_kind = overlapping.iter().copied().find(|i| {
+                    matches!(&self.ours[*i].1, Some(o) if discriminant(o) == discriminant(&item) && o.uid().is_some())
+                });
+                match same_kind {
+                    Some(index) => self.replace(index, item),
+                    None => self.add(reference, item),
+                }
+                for i in overlapping.into_iter().filter(|i| Some(*i) != same_kind) {
+                    self.ours[i].1 = None;
+                }
+            }
+            Resolution::KeepBoth => self.add(reference, item),
+        }
+    }
+}
+
+impl XDFFormat {
+    /// Merges the items of `other` into this definition, conflicts are resolved according to `policy`.
+    /// Our header is kept, only its categories are extended. If there is no header, theirs is used.
+    pub fn merge(&mut self, other: &XDFFormat, policy: MergePolicy) -> MergeReport {
+        let categories = self.merge_categories(other);
+        let theirs: Vec<_> = items(other)
+            .into_iter()
+            .map(|(reference, mut item)| {
+                for membership in item.catmem_mut().into_iter().flatten() {
+                    if let Some(new) = membership.category.and_then(|c| categories.get(&c)) {
+                        membership.category = Some(*new);
+                    }
+                }
+                (reference, item)
+            })
+            .collect();
+
+        let mut merger = Merger {
+            ours: items(self)
+                .into_iter()
+                .map(|(reference, item)| (reference, Some(item)))
+                .collect(),
+            incoming: Vec::new(),
+            registry: self.ids(),
+            report: MergeReport::default(),
+        };
+        let original = merger.ours.len();
+
+        // Ids of matched items, to compare linked axes before the final ids are known
+        let matches: Vec<_> = theirs
+            .iter()
+            .map(|(_, item)| find_match(&merger.ours, item))
+            .collect();
+        let mut links = HashMap::new();
+        for ((_, item), found) in theirs.iter().zip(&matches) {
+            if let (Some(from), Some(to)) = (item.uid(), found.and_then(|i| merger.uid(i))) {
+                links.entry(from).or_insert(to);
+            }
+        }
+
+        for ((reference, item), found) in theirs.into_iter().zip(matches) {
+            match found {
+                Some(index) => merger.merge_matched(index, reference, item, &links, policy),
+                None => merger.merge_unmatched(original, reference, item, policy),
+            }
+        }
+
+        let Merger {
+            mut ours,
+            incoming,
+            report,
+            ..
+        } = merger;
+        for index in incoming {
+            if let Some(XdfItem::Table(table)) = &mut ours[index].1 {
+                remap_links(table, &report.ids);
+            }
+        }
+        self.replace_items(ours.into_iter().filter_map(|(_, item)| item));
+        report
+    }
+
+    /// Adds their categories missing from our header, matched by name.
+    /// Returns the new index of each of their category indices.
+    fn merge_categories(&mut self, other: &XDFFormat) -> HashMap<u32, u32> {
+        let mut mapping = HashMap::new();
+        let Some(theirs) = &other.header else {
+            return mapping;
+        };
+        let Some(ours) = &mut self.header else {
+            self.header = Some(theirs.clone());
+            return mapping;
+        };
+        for category in &theirs.category {
+            let Some(index) = category.index else {
+                continue;
+            };
+            let existing = ours
+                .category
+                .iter()
+                .find(|c| c.name.is_some() && c.name == category.name)
+                .and_then(|c| c.index);
+            let used = |i: u32| ours.category.iter().any(|c| c.index == Some(i));
+            let new = match existing {
+                Some(existing) => existing,
+                None => {
+                    let new = if used(index) {
+                        (0..)
+                            .find(|i| !used(*i))
+                            .expect("ran out of category indices")
+                    } else {
+                        index
+                    };
+                    ours.category.push(Category {
+                        index: Some(new),
+                        name: category.name.clone(),
+                    });
+                    new
+                }
+            };
+            mapping.insert(index, new);
+        }
+        mapping
+    }
+}
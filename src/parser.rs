@@ -2,16 +2,205 @@
 //! This is likely not the best way of doing this, but it was fairly easy to write as a MVP.
 //! Should be rewritten later.
 
-use std::str::FromStr;
+use std::{fs::File, io::Read, path::Path, str::FromStr};
 
-use xml::{attribute::OwnedAttribute, name::OwnedName, reader::XmlEvent, EventReader};
+use xml::{
+    attribute::OwnedAttribute, common::Position, common::TextPosition, name::OwnedName,
+    reader::XmlEvent, EventReader,
+};
 
-use crate::{data_types::*, error::Error};
+use crate::{
+    data_types::*,
+    decode::{attr, decode, required_attr, Decode},
+    encoding::Encoding,
+    error::{Error, ErrorKind, Warning, WarningKind},
+    reader::{XdfItem, XdfReader},
+};
+
+/// Controls how the parser reads its input and handles content it does not understand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Fail on unknown or misplaced elements.
+    /// When false unknown elements are reported as warnings along with misplaced elements,
+    /// and kept as raw nodes in the `extras` of their parent where it has one.
+    /// Unknown attributes never fail the parse, they are always reported as warnings.
+    pub strict: bool,
+    /// Character encoding of the input, detected by default
+    pub encoding: Encoding,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            strict: true,
+            encoding: Encoding::Auto,
+        }
+    }
+}
+
+/// Parser state that lives across calls into the parser, tracks which elements are currently open so errors can be located.
+#[derive(Debug, Default)]
+pub(crate) struct ParseState {
+    /// Path segments of the currently open elements along with the position of their start tags
+    open: Vec<(String, TextPosition)>,
+    options: ParseOptions,
+    pub(crate) warnings: Vec<Warning>,
+}
+
+impl ParseState {
+    pub(crate) fn new(options: ParseOptions) -> Self {
+        Self {
+            options,
+            ..Default::default()
+        }
+    }
+}
+
+/// Reader and state passed through the element functions.
+pub(crate) struct Context<'a, R: Read> {
+    reader: &'a mut EventReader<R>,
+    state: &'a mut ParseState,
+}
+
+impl<'a, R: Read> Context<'a, R> {
+    pub(crate) fn new(reader: &'a mut EventReader<R>, state: &'a mut ParseState) -> Self {
+        Self { reader, state }
+    }
+
+    /// Records a warning against the innermost open element.
+    fn warn(&mut self, kind: WarningKind) {
+        self.state.warnings.push(Warning {
+            kind,
+            position: self.state.open.last().map(|(_, position)| *position),
+            path: self.path(),
+        });
+    }
+
+    /// Handles an element that is not valid as a child of the current element.
+    /// Fails in strict mode, otherwise the element is dropped with a warning.
+    pub(crate) fn unexpected(&mut self, element: XDFElement) -> Result<(), Error> {
+        match element {
+            // Already reported when it was read
+            XDFElement::Unknown(_) => Ok(()),
+            e if self.state.options.strict => Err(ErrorKind::UnexpectedElement(e).into()),
+            e => {
+                self.warn(WarningKind::UnexpectedElement(e));
+                Ok(())
+            }
+        }
+    }
+
+    /// Reports any attributes that are not in the list of attributes known for the current element.
+    fn check_attributes(&mut self, known: &[&str], attributes: &[OwnedAttribute]) {
+        for attribute in attributes {
+            if !known.contains(&attribute.name.local_name.as_str()) {
+                self.warn(WarningKind::UnknownAttribute {
+                    name: attribute.name.local_name.clone(),
+                    value: attribute.value.clone(),
+                });
+            }
+        }
+    }
+
+    /// Reads the rest of an element whose start tag has already been consumed as a raw node.
+    fn read_raw(
+        &mut self,
+        name: &OwnedName,
+        attributes: &[OwnedAttribute],
+    ) -> Result<RawElement, Error> {
+        let mut open = vec![RawElement::new(name, attributes)];
+        loop {
+            match self.next()? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => open.push(RawElement::new(&name, &attributes)),
+                XmlEvent::EndElement { .. } => {
+                    let done = open.pop().expect("raw element stack is never empty");
+                    match open.last_mut() {
+                        Some(parent) => parent.children.push(RawNode::Element(done)),
+                        None => return Ok(done),
+                    }
+                }
+                XmlEvent::Characters(text) => {
+                    if let Some(current) = open.last_mut() {
+                        current.children.push(RawNode::Text(text))
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub(crate) fn next(&mut self) -> Result<XmlEvent, Error> {
+        Ok(self.reader.next()?)
+    }
+
+    /// Element path of the innermost open element, e.g. `XDFFORMAT/XDFTABLE[uid=0x6E81]/XDFAXIS[z]`
+    fn path(&self) -> String {
+        self.state
+            .open
+            .iter()
+            .map(|(segment, _)| segment.as_str())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Whether the element enclosing the innermost open element is a `tag` element.
+    fn parent_is(&self, tag: &str) -> bool {
+        let open = &self.state.open;
+        open.len() >= 2 && {
+            let (segment, _) = &open[open.len() - 2];
+            segment
+                .split('[')
+                .next()
+                .unwrap_or_default()
+                .eq_ignore_ascii_case(tag)
+        }
+    }
+
+    /// Marks an element as open, must be followed by a call to `leave` once the element has been consumed.
+    pub(crate) fn enter(&mut self, name: &OwnedName, attributes: &[OwnedAttribute]) {
+        let find = |n: &str| {
+            attributes
+                .iter()
+                .find(|a| a.name.local_name == n)
+                .map(|a| a.value.as_str())
+        };
+        let segment = if let Some(id) = find("id") {
+            format!("{}[{id}]", name.local_name)
+        } else if let Some(uid) = find("uniqueid") {
+            format!("{}[uid={uid}]", name.local_name)
+        } else {
+            name.local_name.clone()
+        };
+        self.state.open.push((segment, self.reader.position()));
+    }
+
+    /// Locates an error against the innermost open element and closes it, successful results leave the element open.
+    pub(crate) fn leave_on_error<T>(&mut self, result: Result<T, Error>) -> Result<T, Error> {
+        match result {
+            Ok(v) => Ok(v),
+            e => self.leave(e),
+        }
+    }
+
+    /// Closes the innermost element, locating any error that occurred inside of it.
+    pub(crate) fn leave<T>(&mut self, result: Result<T, Error>) -> Result<T, Error> {
+        let result = match result {
+            Err(e) if let Some((_, position)) = self.state.open.last() => {
+                Err(e.locate(*position, || self.path()))
+            }
+            r => r,
+        };
+        self.state.open.pop();
+        result
+    }
+}
 
 /// Reads a string from an XML characters event, used to parse data stored within an element rather than as an attribute.
 /// e.g. `<title>DATAHERE</title>`
 /// If there is no data present an empty string is returned.
-fn from_chars<R: std::io::Read>(parser: &mut EventReader<R>) -> Result<String, Error> {
+fn from_chars<R: Read>(parser: &mut Context<R>) -> Result<String, Error> {
     let next = parser.next()?;
     let mut end = false;
     let res = if let xml::reader::XmlEvent::Characters(chars) = next {
@@ -20,7 +209,7 @@ fn from_chars<R: std::io::Read>(parser: &mut EventReader<R>) -> Result<String, E
         end = true;
         Ok(String::new())
     } else {
-        Err(crate::error::Error::UnexpectedEvent(next))
+        Err(ErrorKind::UnexpectedEvent(next).into())
     };
     if !end {
         let _ = parser.next()?;
@@ -28,46 +217,17 @@ fn from_chars<R: std::io::Read>(parser: &mut EventReader<R>) -> Result<String, E
     res
 }
 
-/// Parses an integer, stored in base10 or base16 ASCII.
-/// If string starts with `0x` assumes base16, otherwise base10.
-fn parse_int(from: &str) -> Result<u32, Error> {
-    let stripped = from.strip_prefix("0x");
-    if let Some(hex) = stripped {
-        u32::from_str_radix(hex, 16).map_err(|_| panic!("Error::BadValue"))
-    } else {
-        from.parse().map_err(|_| panic!("Error::BadValue"))
-    }
-}
-
-/// Convenience function to use `parse_int` on the output of `get_attr`
-fn int_attr(attrs: &Vec<OwnedAttribute>, name: &str) -> Result<u32, Error> {
-    parse_int(&get_attr(attrs, name)?)
-}
-
-/// Convenience function to parse the output of `from_chars`
-fn parse_chars<R: std::io::Read, T: FromStr>(parser: &mut EventReader<R>) -> Result<T, Error> {
-    from_chars(parser)?.parse().map_err(|_| Error::BadValue)
-}
-
-/// Gets the string value of a named attribute
-fn get_attr(attrs: &Vec<OwnedAttribute>, name: &str) -> Result<String, Error> {
-    for attr in attrs {
-        if attr.name.local_name == name {
-            return Ok(attr.value.clone());
-        }
-    }
-    return Err(Error::MissingItem);
-}
-
-/// Convenience function to parse the output of `get_attr`, use `int_attr` instead when possible.
-fn get_attr_parse<T: FromStr>(attrs: &Vec<OwnedAttribute>, name: &str) -> Result<T, Error> {
-    get_attr(attrs, name)?.parse().map_err(|_| Error::BadValue)
+/// Convenience function to decode the output of `from_chars`
+fn chars<R: Read, T: Decode>(parser: &mut Context<R>) -> Result<T, Error> {
+    decode(&from_chars(parser)?)
 }
 
 /// Creates a function that builds an object by looping over an XmlReader.
-/// Has three ways of defining a field, either from another type of known XMLElement that can be parsed by `XDFElement::from_xml`.
+/// Has three ways of defining a field, either from another type of known XMLElement that can be parsed by `XDFElement::parse`.
 /// Or, a list of elements of the same type.
 /// Or, an element that requires an external function to parse, usually stored in an attribute.
+/// Types with an `extras` field take the unknown attributes as a trailing `extras: expr`, unknown children are collected automatically
+/// along with their position. Other types leave unknown children to `Context::unexpected`, which has already reported them.
 /// The generated function consumes any end of element events.
 macro_rules! build_obj {
     ($parser:ident,$name:expr,$type:ident,[$($fieldname:ident ; $fieldsource:ident),*]) => {build_obj!($parser, $name, $type, [$($fieldname;$fieldsource),*],[],[])};
@@ -81,7 +241,7 @@ macro_rules! build_obj {
             )*
 
             loop {
-                match XDFElement::from_xml($parser)? {
+                match XDFElement::parse($parser)? {
                     $(
                         XDFElement::$fieldsource(v) => $fieldname = Some(v),
                     )*
@@ -93,9 +253,9 @@ macro_rules! build_obj {
                     } else {
                         continue;
                     }
-                    e => return Err(Error::UnexpectedElement(e)),
-                }
+                    e => $parser.unexpected(e)?,
                 }
+            }
 
             XDFElement::$type($type {
                 $(
@@ -110,205 +270,468 @@ macro_rules! build_obj {
             })
         }
     };
-    ($parser:ident,$type:ident,[$($fname:ident:$fsource:expr,)*]) => {{
-        let r = XDFElement::$type($type {
+    ($parser:ident,$name:expr,$type:ident,[$($fieldname:ident ; $fieldsource:ident),*], [$($fieldn:ident ; $fieldcalc:block),*],[$($vfname:ident;$vfsource:ident),*], extras: $extras:expr) => {
+        {
             $(
-                $fname: $fsource,
+                let mut $fieldname = None;
             )*
-        });
-        let next = XDFElement::from_xml($parser)?;
-        if let XDFElement::End(_) = next {
-            r
-        } else {
-            Err(Error::UnexpectedElement(next))?
+            $(
+                let mut $vfname = Vec::new();
+            )*
+            let mut unknown = Vec::new();
+            // Children kept so far, known or not
+            let mut position = 0;
+
+            loop {
+                match XDFElement::parse($parser)? {
+                    $(
+                        XDFElement::$fieldsource(v) => $fieldname = Some(v),
+                    )*
+                    $(
+                        XDFElement::$vfsource(v) => $vfname.push(v),
+                    )*
+                    XDFElement::End(name) => if &name == $name {
+                        break;
+                    } else {
+                        continue;
+                    }
+                    XDFElement::Unknown(element) => unknown.push(ExtraChild { position, element }),
+                    e => {
+                        $parser.unexpected(e)?;
+                        continue;
+                    }
+                }
+                position += 1;
+            }
+
+            XDFElement::$type($type {
+                $(
+                    $fieldname: $fieldname,
+                )*
+                $(
+                    $vfname: $vfname,
+                )*
+                $(
+                    $fieldn: $fieldcalc,
+                )*
+                extras: Extras {
+                    attributes: $extras,
+                    children: unknown,
+                },
+            })
         }
-    }}
+    };
+    ($parser:ident,$type:ident,[$($fname:ident:$fsource:expr,)*]) => {{
+        let r = loop {
+            match XDFElement::parse($parser)? {
+                XDFElement::End(_) => break $type {
+                    $(
+                        $fname: $fsource,
+                    )*
+                },
+                e => $parser.unexpected(e)?,
+            }
+        };
+        XDFElement::$type(r)
+    }};
+    ($parser:ident,$type:ident,[$($fname:ident:$fsource:expr,)*], extras: $extras:expr) => {{
+        let mut unknown = Vec::new();
+        let r = loop {
+            match XDFElement::parse($parser)? {
+                XDFElement::End(_) => break $type {
+                    $(
+                        $fname: $fsource,
+                    )*
+                    extras: Extras {
+                        attributes: $extras,
+                        children: unknown,
+                    },
+                },
+                XDFElement::Unknown(element) => unknown.push(ExtraChild {
+                    position: unknown.len() as u32,
+                    element,
+                }),
+                e => $parser.unexpected(e)?,
+            }
+        };
+        XDFElement::$type(r)
+    }};
+}
+
+impl XDFFormat {
+    /// Parses an XDF document, failing if the root element is not XDFFORMAT.
+    /// Unknown elements fail the parse, use `parse_with_options` with `strict: false` to keep them in `extras`.
+    pub fn parse<R: Read>(from: R) -> Result<Self, Error> {
+        Ok(Self::parse_with_options(from, ParseOptions::default())?.0)
+    }
+
+    /// Same as `parse`, but with control over how the input is read and how unknown content is handled.
+    /// Returns any warnings produced while parsing along with the document.
+    pub fn parse_with_options<R: Read>(
+        from: R,
+        options: ParseOptions,
+    ) -> Result<(Self, Vec<Warning>), Error> {
+        let mut reader = XdfReader::with_options(from, options);
+        let mut format = XDFFormat::default();
+        for item in reader.items() {
+            format.add_item(item?);
+        }
+        format.version = reader.version().map(str::to_string);
+        Ok((format, reader.into_warnings()))
+    }
+
+    /// Adds an item to the list for its type, a header replaces any existing header.
+    pub(crate) fn add_item(&mut self, item: XdfItem) {
+        match item {
+            XdfItem::Header(v) => self.header = Some(v),
+            XdfItem::Table(v) => self.tables.push(v),
+            XdfItem::Constant(v) => self.constants.push(v),
+            XdfItem::Patch(v) => self.patches.push(v),
+            XdfItem::Flag(v) => self.flags.push(v),
+            XdfItem::Checksum(v) => self.checksums.push(v),
+        }
+    }
+
+    /// Replaces every item other than the header.
+    pub(crate) fn replace_items(&mut self, items: impl IntoIterator<Item = XdfItem>) {
+        self.tables.clear();
+        self.constants.clear();
+        self.patches.clear();
+        self.flags.clear();
+        self.checksums.clear();
+        for item in items {
+            self.add_item(item);
+        }
+    }
+
+    /// Opens and parses an XDF file.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::parse(File::open(path)?)
+    }
+}
+
+impl FromStr for XDFFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s.as_bytes())
+    }
 }
 
 impl XDFElement {
     /// Parses either an entire XDF document, or a single element (including all it's children)
-    pub fn from_xml<R: std::io::Read>(parser: &mut EventReader<R>) -> Result<Self, Error> {
-        let next;
+    pub fn from_xml<R: Read>(parser: &mut EventReader<R>) -> Result<Self, Error> {
+        let mut state = ParseState::default();
+        Self::parse(&mut Context::new(parser, &mut state))
+    }
+
+    /// Same as `from_xml`, but with control over how unknown content is handled.
+    /// Returns any warnings produced while parsing along with the element.
+    pub fn from_xml_with_options<R: Read>(
+        parser: &mut EventReader<R>,
+        options: ParseOptions,
+    ) -> Result<(Self, Vec<Warning>), Error> {
+        let mut state = ParseState::new(options);
+        let element = Self::parse(&mut Context::new(parser, &mut state))?;
+        Ok((element, state.warnings))
+    }
+
+    /// Parses the next element, returning `XDFElement::End` if the next event closes the current element.
+    pub(crate) fn parse<R: Read>(parser: &mut Context<R>) -> Result<Self, Error> {
         loop {
-            let current = parser.next()?;
-            if let XmlEvent::StartDocument { .. } = &current {
-                continue;
-            }
-            next = Some(match current {
-                xml::reader::XmlEvent::StartElement {
+            return match parser.next()? {
+                XmlEvent::StartDocument { .. } => continue,
+                XmlEvent::StartElement {
                     name, attributes, ..
-                } => match name.local_name.to_lowercase().as_str() {
-                    "title" | "deftitle" => Self::Title(from_chars(parser)?),
-                    "description" => Self::Description(from_chars(parser)?),
-                    "units" => Self::Units(from_chars(parser)?),
-                    "author" => Self::Author(from_chars(parser)?),
-                    "fileversion" => Self::FileVersion(from_chars(parser)?),
-                    "indexcount" => Self::IndexCount(parse_chars(parser)?),
-                    "datatype" => Self::DataType(parse_chars(parser)?),
-                    "unittype" => Self::UnitType(parse_chars(parser)?),
-                    "outputtype" => Self::OutputType(parse_int(&from_chars(parser)?)?),
-                    "decimalpl" => Self::DecimalPl(parse_chars(parser)?),
-                    "flags" => Self::Flags(parse_int(&from_chars(parser)?)?),
-                    "min" => Self::Min(parse_chars(parser)?),
-                    "max" => Self::Max(parse_chars(parser)?),
-                    "baseoffset" => {
-                        if let Ok(offset) = get_attr_parse(&attributes, "offset") {
-                            Self::BaseOffset(offset)
-                        } else {
-                            Self::BaseOffset(parse_int(&from_chars(parser)?)?)
-                        }
-                    }
-                    "dalink" => {
-                        let r = Self::DALink(get_attr_parse(&attributes, "index")?);
-                        parser.next()?;
-                        r
-                    }
-                    "var" => {
-                        let r = Self::Var(get_attr_parse(&attributes, "id")?);
-                        parser.next()?;
-                        r
-                    }
-                    "embedinfo" => build_obj!(parser, EmbedInfo, [
-                        etype: int_attr(&attributes, "type").ok(),
-                        linkobjid: int_attr(&attributes, "linkobjid").ok(),
-                    ]),
-                    "defaults" => build_obj!(parser, Defaults, [
-                        datasizeinbits: int_attr(&attributes, "datasizeinbits").ok(),
-                        sigdigits: int_attr(&attributes, "sigdigits").ok(),
-                        outputtype: int_attr(&attributes, "outputtype").ok(),
-                        signed: int_attr(&attributes, "signed").ok(),
-                        lsbfirst: int_attr(&attributes, "lsbfirst").ok(),
-                        float: int_attr(&attributes, "float").ok(),
-                    ]),
-                    "category" => build_obj!(parser, Category,[
-                        index: int_attr(&attributes, "index").ok(),
-                        name: get_attr(&attributes, "name").ok(),
-                    ]),
-                    "region" => build_obj!(parser, Region,[
-                        rtype: int_attr(&attributes, "type").ok(),
-                        startaddress: int_attr(&attributes, "startaddress").ok(),
-                        size: int_attr(&attributes, "size").ok(),
-                        regionflags: int_attr(&attributes, "regionflags").ok(),
-                    ]),
-                    "categorymem" => build_obj!(parser, CategoryMem, [
-                        index: get_attr_parse(&attributes, "index").ok(),
-                        category: get_attr_parse(&attributes, "category").ok(),
-                    ]),
-                    "embeddeddata" => build_obj!(parser, EmbeddedData, [
-                        mmedaddress: int_attr(&attributes, "mmedaddress").ok(),
-                        mmedelementsizebits: get_attr_parse(&attributes, "mmedelementsizebits").ok(),
-                        mmedmajorstridebits: get_attr_parse(&attributes, "mmedmajorstridebits").ok(),
-                        mmedminorstridebits: get_attr_parse(&attributes, "mmedminorstridebits").ok(),
-                        mmedtypeflags: get_attr_parse(&attributes, "mmedtypeflags").ok(),
-                        mmedrowcount: get_attr_parse(&attributes, "mmedrowcount").ok(),
-                        mmedcolcount: get_attr_parse(&attributes, "mmedcolcount").ok(),
-                    ]),
-                    "label" => build_obj!(parser, Label, [
-                        index: get_attr_parse(&attributes, "index").ok(),
-                        value: get_attr_parse(&attributes, "value").ok(),
-                    ]),
-                    "math" => build_obj!(
-                        parser,
-                        "math",
-                        Math,
-                        [],
-                        [expression; { get_attr_parse(&attributes, "equation").ok() }],
-                        [vars; Var]
-                    ),
-                    "xdfformat" => build_obj!(parser, "xdfformat", XDFFormat, [
-                        header; XDFHeader
-                    ], [
-                        version; {get_attr_parse(&attributes, "version").ok()}
-                    ], [
-                        constants; XDFConstant,
-                        tables; XDFTable
-                    ]),
-                    "xdftable" => build_obj!(parser, "xdftable", XDFTable, [
-                        title; Title,
-                        flags; Flags,
-                        description; Description
-                    ],[
-                        uid; {int_attr(&attributes, "uniqueid").ok()}
-                    ],[
-                        catmem; CategoryMem,
-                        axis; XDFAxis
-                    ]),
-                    "xdfheader" => build_obj!(parser, "xdfheader", XDFHeader, [
-                        deftitle; Title,
-                        description; Description,
-                        baseoffset; BaseOffset,
-                        defaults; Defaults,
-                        region; Region,
-                        flags; Flags,
-                        fileversion; FileVersion,
-                        author; Author
-                    ],[],[
-                        category; Category
-                    ]),
-                    "xdfaxis" => build_obj!(parser, "xdfaxis", XDFAxis, [
-                        embeddeddata; EmbeddedData,
-                        min; Min,
-                        max; Max,
-                        outputtype; OutputType,
-                        datatype; DataType,
-                        unittype; UnitType,
-                        dalink_index; DALink,
-                        count; IndexCount,
-                        decimalplaces; DecimalPl,
-                        math; Math,
-                        unit; Units,
-                        embedinfo; EmbedInfo
-                    ], [
-                        id; {get_attr(&attributes, "id").ok()},
-                        uid; {int_attr(&attributes, "uniqueid").ok()}
-                    ],[
-                        labels; Label
-                    ]),
-                    "xdfconstant" => build_obj!(parser, "xdfconstant", XDFConstant, [
-                        embedded_data; EmbeddedData,
-                        title; Title,
-                        description; Description,
-                        outputtype; OutputType,
-                        datatype; DataType,
-                        decimalplaces; DecimalPl,
-                        unittype; UnitType,
-                        unit; Units,
-                        math; Math,
-                        dalink_index; DALink
-                    ],[
-                        uid; {get_attr_parse(&attributes, "uniqueid").ok()}
-                    ],[
-                        catmem; CategoryMem
-                    ]),
-                    "xdfpatch" | "xdfflag" | "xdfchecksum" => {
-                        loop {
-                            let event = parser.next()?;
-                            let end1 = XmlEvent::EndElement {
-                                name: OwnedName::local("XDFPATCH"),
-                            };
-                            let end2 = XmlEvent::EndElement {
-                                name: OwnedName::local("XDFFLAG"),
-                            };
-                            let end3 = XmlEvent::EndElement {
-                                name: OwnedName::local("XDFCHECKSUM"),
-                            };
-                            if event == end1 || event == end2 || event == end3 {
-                                break;
-                            }
-                        }
-                        continue;
-                    }
-                    u => {
-                        dbg!(u);
-                        return Err(Error::UnknownType);
-                    }
-                },
-                XmlEvent::EndElement { name } => Self::End(name.local_name.to_lowercase()),
-                e => return Err(Error::UnexpectedEvent(e)),
-            });
-            break;
+                } => {
+                    parser.enter(&name, &attributes);
+                    let element = Self::parse_element(parser, &name, &attributes);
+                    parser.leave(element)
+                }
+                XmlEvent::EndElement { name } => Ok(Self::End(name.local_name.to_lowercase())),
+                e => Err(ErrorKind::UnexpectedEvent(e).into()),
+            };
         }
+    }
 
-        Ok(next.unwrap())
+    /// Parses the contents of an element after its start tag has been read, consuming its end tag.
+    fn parse_element<R: Read>(
+        parser: &mut Context<R>,
+        name: &OwnedName,
+        attributes: &[OwnedAttribute],
+    ) -> Result<Self, Error> {
+        let tag = name.local_name.to_lowercase();
+        if let Some(known) = known_attributes(&tag) {
+            parser.check_attributes(known, attributes);
+        }
+        Ok(match tag.as_str() {
+            "title" | "deftitle" => Self::Title(from_chars(parser)?),
+            "description" => Self::Description(from_chars(parser)?),
+            "units" => Self::Units(from_chars(parser)?),
+            "author" => Self::Author(from_chars(parser)?),
+            "fileversion" => Self::FileVersion(from_chars(parser)?),
+            "indexcount" => Self::IndexCount(chars(parser)?),
+            "datatype" => Self::DataType(chars(parser)?),
+            "unittype" => Self::UnitType(chars(parser)?),
+            "outputtype" => Self::OutputType(chars(parser)?),
+            "decimalpl" => Self::DecimalPl(chars(parser)?),
+            "flags" => Self::Flags(chars(parser)?),
+            "mask" => Self::Mask(chars(parser)?),
+            "datastart" => Self::DataStart(chars(parser)?),
+            "dataend" => Self::DataEnd(chars(parser)?),
+            "datasizebits" => Self::DataSizeBits(chars(parser)?),
+            "storeaddress" => Self::StoreAddress(chars(parser)?),
+            "calculationmethod" => Self::CalculationMethod(chars(parser)?),
+            "min" => Self::Min(chars(parser)?),
+            "max" => Self::Max(chars(parser)?),
+            "baseoffset" => {
+                if let Some(offset) = attr(attributes, "offset")? {
+                    Self::BaseOffset(offset)
+                } else {
+                    Self::BaseOffset(chars(parser)?)
+                }
+            }
+            "dalink" => {
+                let r = Self::DALink(required_attr(attributes, "index")?);
+                parser.next()?;
+                r
+            }
+            "var" => {
+                let r = Self::Var(required_attr(attributes, "id")?);
+                parser.next()?;
+                r
+            }
+            "embedinfo" => build_obj!(parser, EmbedInfo, [
+                etype: attr(attributes, "type")?,
+                linkobjid: attr(attributes, "linkobjid")?,
+            ]),
+            "defaults" => build_obj!(parser, Defaults, [
+                datasizeinbits: attr(attributes, "datasizeinbits")?,
+                sigdigits: attr(attributes, "sigdigits")?,
+                outputtype: attr(attributes, "outputtype")?,
+                signed: attr(attributes, "signed")?,
+                lsbfirst: attr(attributes, "lsbfirst")?,
+                float: attr(attributes, "float")?,
+            ]),
+            "category" => build_obj!(parser, Category,[
+                index: attr(attributes, "index")?,
+                name: attr(attributes, "name")?,
+            ]),
+            // Checksum regions store their values as children, header regions as attributes, either may have neither.
+            "region" if parser.parent_is("xdfchecksum") => {
+                build_obj!(parser, "region", ChecksumRegion, [
+                        datastart; DataStart,
+                        dataend; DataEnd,
+                        datasizebits; DataSizeBits,
+                        storeaddress; StoreAddress,
+                        calculationmethod; CalculationMethod
+                    ],[],[])
+            }
+            "region" => build_obj!(parser, Region,[
+                rtype: attr(attributes, "type")?,
+                startaddress: attr(attributes, "startaddress")?,
+                size: attr(attributes, "size")?,
+                regionflags: attr(attributes, "regionflags")?,
+            ]),
+            "categorymem" => build_obj!(parser, CategoryMem, [
+                index: attr(attributes, "index")?,
+                category: attr(attributes, "category")?,
+            ]),
+            "embeddeddata" => build_obj!(parser, EmbeddedData, [
+                mmedaddress: attr(attributes, "mmedaddress")?,
+                mmedelementsizebits: attr(attributes, "mmedelementsizebits")?,
+                mmedmajorstridebits: attr(attributes, "mmedmajorstridebits")?,
+                mmedminorstridebits: attr(attributes, "mmedminorstridebits")?,
+                mmedtypeflags: attr(attributes, "mmedtypeflags")?,
+                mmedrowcount: attr(attributes, "mmedrowcount")?,
+                mmedcolcount: attr(attributes, "mmedcolcount")?,
+            ], extras: unknown_attributes("embeddeddata", attributes)),
+            "label" => build_obj!(parser, Label, [
+                index: attr(attributes, "index")?,
+                value: attr(attributes, "value")?,
+            ]),
+            "math" => build_obj!(
+                parser,
+                "math",
+                Math,
+                [],
+                [expression; { attr(attributes, "equation")? }],
+                [vars; Var]
+            ),
+            "xdfformat" => build_obj!(parser, "xdfformat", XDFFormat, [
+                header; XDFHeader
+            ], [
+                version; {attr(attributes, "version")?}
+            ], [
+                constants; XDFConstant,
+                tables; XDFTable,
+                patches; XDFPatch,
+                flags; XDFFlag,
+                checksums; XDFChecksum
+            ]),
+            "xdftable" => {
+                // TunerPro writes flags as an attribute, older definitions as a child element
+                let flags = attr(attributes, "flags")?;
+                let table = build_obj!(parser, "xdftable", XDFTable, [
+                    title; Title,
+                    description; Description,
+                    flags; Flags
+                ],[
+                    uid; {attr(attributes, "uniqueid")?}
+                ],[
+                    catmem; CategoryMem,
+                    axis; XDFAxis
+                ], extras: unknown_attributes("xdftable", attributes));
+                match table {
+                    XDFElement::XDFTable(mut table) if flags.is_some() => {
+                        table.flags = flags;
+                        XDFElement::XDFTable(table)
+                    }
+                    table => table,
+                }
+            }
+            "xdfheader" => build_obj!(parser, "xdfheader", XDFHeader, [
+                deftitle; Title,
+                description; Description,
+                baseoffset; BaseOffset,
+                defaults; Defaults,
+                region; Region,
+                flags; Flags,
+                fileversion; FileVersion,
+                author; Author
+            ],[],[
+                category; Category
+            ], extras: unknown_attributes("xdfheader", attributes)),
+            "xdfaxis" => build_obj!(parser, "xdfaxis", XDFAxis, [
+                embeddeddata; EmbeddedData,
+                min; Min,
+                max; Max,
+                outputtype; OutputType,
+                datatype; DataType,
+                unittype; UnitType,
+                dalink_index; DALink,
+                count; IndexCount,
+                decimalplaces; DecimalPl,
+                math; Math,
+                unit; Units,
+                embedinfo; EmbedInfo
+            ], [
+                id; {attr(attributes, "id")?},
+                uid; {attr(attributes, "uniqueid")?}
+            ],[
+                labels; Label
+            ], extras: unknown_attributes("xdfaxis", attributes)),
+            "xdfconstant" => build_obj!(parser, "xdfconstant", XDFConstant, [
+                embedded_data; EmbeddedData,
+                title; Title,
+                description; Description,
+                outputtype; OutputType,
+                datatype; DataType,
+                decimalplaces; DecimalPl,
+                unittype; UnitType,
+                unit; Units,
+                math; Math,
+                dalink_index; DALink
+            ],[
+                uid; {attr(attributes, "uniqueid")?}
+            ],[
+                catmem; CategoryMem
+            ], extras: unknown_attributes("xdfconstant", attributes)),
+            "xdfpatch" => build_obj!(parser, "xdfpatch", XDFPatch, [
+                title; Title,
+                description; Description
+            ],[
+                uid; {attr(attributes, "uniqueid")?}
+            ],[
+                catmem; CategoryMem,
+                entries; XDFPatchEntry
+            ]),
+            "xdfpatchentry" => build_obj!(parser, XDFPatchEntry, [
+                name: attr(attributes, "name")?,
+                address: attr(attributes, "address")?,
+                datasize: attr(attributes, "datasize")?,
+                patchdata: attr(attributes, "patchdata")?,
+                basedata: attr(attributes, "basedata")?,
+            ]),
+            "xdfflag" => build_obj!(parser, "xdfflag", XDFFlag, [
+                title; Title,
+                description; Description,
+                embedded_data; EmbeddedData,
+                mask; Mask
+            ],[
+                uid; {attr(attributes, "uniqueid")?}
+            ],[
+                catmem; CategoryMem
+            ]),
+            "xdfchecksum" => build_obj!(parser, "xdfchecksum", XDFChecksum, [
+                title; Title,
+                description; Description
+            ],[
+                uid; {attr(attributes, "uniqueid")?}
+            ],[
+                catmem; CategoryMem,
+                regions; ChecksumRegion
+            ]),
+            _ if parser.state.options.strict => {
+                return Err(ErrorKind::UnknownType(name.local_name.clone()).into())
+            }
+            _ => {
+                let raw = parser.read_raw(name, attributes)?;
+                parser.warn(WarningKind::UnknownElement(raw.clone()));
+                Self::Unknown(raw)
+            }
+        })
     }
 }
+
+/// Attributes of a known element that are not read by the parser, as name and value pairs.
+fn unknown_attributes(element: &str, attributes: &[OwnedAttribute]) -> Vec<(String, String)> {
+    let known = known_attributes(element).unwrap_or_default();
+    attributes
+        .iter()
+        .filter(|a| !known.contains(&a.name.local_name.as_str()))
+        .map(|a| (a.name.local_name.clone(), a.value.clone()))
+        .collect()
+}
+
+/// Attributes read by the parser for each known element, `None` if the element is not known.
+fn known_attributes(element: &str) -> Option<&'static [&'static str]> {
+    Some(match element {
+        "xdfformat" => &["version"],
+        "xdftable" => &["uniqueid", "flags"],
+        "xdfaxis" => &["id", "uniqueid"],
+        "xdfconstant" | "xdfpatch" | "xdfflag" | "xdfchecksum" => &["uniqueid"],
+        "xdfpatchentry" => &["name", "address", "datasize", "patchdata", "basedata"],
+        "embeddeddata" => &[
+            "mmedaddress",
+            "mmedelementsizebits",
+            "mmedmajorstridebits",
+            "mmedminorstridebits",
+            "mmedtypeflags",
+            "mmedrowcount",
+            "mmedcolcount",
+        ],
+        "embedinfo" => &["type", "linkobjid"],
+        "defaults" => &[
+            "datasizeinbits",
+            "sigdigits",
+            "outputtype",
+            "signed",
+            "lsbfirst",
+            "float",
+        ],
+        "category" => &["index", "name"],
+        "region" => &["type", "startaddress", "size", "regionflags"],
+        "categorymem" => &["index", "category"],
+        "label" => &["index", "value"],
+        "math" => &["equation"],
+        "baseoffset" => &["offset"],
+        "dalink" => &["index"],
+        "var" => &["id"],
+        "title" | "deftitle" | "description" | "units" | "author" | "fileversion"
+        | "indexcount" | "datatype" | "unittype" | "outputtype" | "decimalpl" | "flags"
+        | "mask" | "datastart" | "dataend" | "datasizebits" | "storeaddress"
+        | "calculationmethod" | "min" | "max" | "xdfheader" => &[],
+        _ => return None,
+    })
+}
@@ -0,0 +1,215 @@
+//! Pull based reading of XDF documents, one item at a time.
+//! Useful for very large definition files, or when only a few items are needed.
+
+use std::io::{BufReader, Read};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use xml::{reader::XmlEvent, EventReader};
+
+use crate::{
+    data_types::*,
+    decode::attr,
+    encoding::Transcoder,
+    error::{Error, ErrorKind, Warning},
+    event_reader,
+    parser::{Context, ParseOptions, ParseState},
+};
+
+/// Top level item of an XDF document, as yielded by `XdfReader::items`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum XdfItem {
+    Header(XDFHeader),
+    Table(XDFTable),
+    Constant(XDFConstant),
+    Patch(XDFPatch),
+    Flag(XDFFlag),
+    Checksum(XDFChecksum),
+}
+
+macro_rules! item_from {
+    ($($variant:ident($type:ident)),*) => {
+        $(
+            impl From<$type> for XdfItem {
+                fn from(v: $type) -> Self {
+                    Self::$variant(v)
+                }
+            }
+        )*
+    };
+}
+
+item_from!(
+    Header(XDFHeader),
+    Table(XDFTable),
+    Constant(XDFConstant),
+    Patch(XDFPatch),
+    Flag(XDFFlag),
+    Checksum(XDFChecksum)
+);
+
+impl XdfItem {
+    /// Converts a parsed element into an item, handing the element back if it is not a top level item.
+    pub(crate) fn from_element(element: XDFElement) -> Result<Self, Box<XDFElement>> {
+        Ok(match element {
+            XDFElement::XDFHeader(v) => Self::Header(v),
+            XDFElement::XDFTable(v) => Self::Table(v),
+            XDFElement::XDFConstant(v) => Self::Constant(v),
+            XDFElement::XDFPatch(v) => Self::Patch(v),
+            XDFElement::XDFFlag(v) => Self::Flag(v),
+            XDFElement::XDFChecksum(v) => Self::Checksum(v),
+            e => return Err(Box::new(e)),
+        })
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        match self {
+            Self::Header(v) => v.deftitle.as_deref(),
+            Self::Table(v) => v.title.as_deref(),
+            Self::Constant(v) => v.title.as_deref(),
+            Self::Patch(v) => v.title.as_deref(),
+            Self::Flag(v) => v.title.as_deref(),
+            Self::Checksum(v) => v.title.as_deref(),
+        }
+    }
+
+    /// `uniqueid` of the item, headers have none.
+    pub fn uid(&self) -> Option<u32> {
+        match self {
+            Self::Header(_) => None,
+            Self::Table(v) => v.uid,
+            Self::Constant(v) => v.uid,
+            Self::Patch(v) => v.uid,
+            Self::Flag(v) => v.uid,
+            Self::Checksum(v) => v.uid,
+        }
+    }
+
+    /// Category memberships of the item, empty for headers.
+    pub fn catmem(&self) -> &[CategoryMem] {
+        match self {
+            Self::Header(_) => &[],
+            Self::Table(v) => &v.catmem,
+            Self::Constant(v) => &v.catmem,
+            Self::Patch(v) => &v.catmem,
+            Self::Flag(v) => &v.catmem,
+            Self::Checksum(v) => &v.catmem,
+        }
+    }
+}
+
+/// Reads the items of an XDF document one at a time rather than building the whole `XDFFormat`.
+pub struct XdfReader<R: Read> {
+    reader: EventReader<BufReader<Transcoder<R>>>,
+    state: ParseState,
+    /// `version` attribute of the root element, available once the first item has been read
+    version: Option<String>,
+    started: bool,
+    finished: bool,
+}
+
+impl<R: Read> XdfReader<R> {
+    pub fn new(from: R) -> Self {
+        Self::with_options(from, ParseOptions::default())
+    }
+
+    pub fn with_options(from: R, options: ParseOptions) -> Self {
+        Self {
+            reader: event_reader(from, options.encoding),
+            state: ParseState::new(options),
+            version: None,
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// Iterator over the items of the document, in document order.
+    /// Iteration stops after the first error.
+    pub fn items(&mut self) -> Items<'_, R> {
+        Items { reader: self }
+    }
+
+    /// `version` attribute of the XDFFORMAT element, `None` until the first item has been read.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Warnings produced by the items read so far.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.state.warnings
+    }
+
+    pub fn into_warnings(self) -> Vec<Warning> {
+        self.state.warnings
+    }
+
+    fn next_item(&mut self) -> Result<Option<XdfItem>, Error> {
+        let mut context = Context::new(&mut self.reader, &mut self.state);
+        if !self.started {
+            self.started = true;
+            self.version = start(&mut context)?;
+        }
+        let item = read_item(&mut context);
+        context.leave_on_error(item)
+    }
+}
+
+/// Consumes the root XDFFORMAT start tag, leaving it open, returns its `version` attribute.
+fn start<R: Read>(context: &mut Context<R>) -> Result<Option<String>, Error> {
+    loop {
+        return match context.next()? {
+            XmlEvent::StartDocument { .. } => continue,
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                context.enter(&name, &attributes);
+                let version = if name.local_name.eq_ignore_ascii_case("xdfformat") {
+                    attr(&attributes, "version")
+                } else {
+                    Err(ErrorKind::UnexpectedRoot(name.local_name.clone()).into())
+                };
+                context.leave_on_error(version)
+            }
+            e => Err(ErrorKind::UnexpectedEvent(e).into()),
+        };
+    }
+}
+
+/// Reads the next item inside the root element, `None` once the root element has been closed.
+fn read_item<R: Read>(context: &mut Context<R>) -> Result<Option<XdfItem>, Error> {
+    loop {
+        return match XdfItem::from_element(XDFElement::parse(context)?) {
+            Ok(item) => Ok(Some(item)),
+            Err(e) if matches!(*e, XDFElement::End(_)) => {
+                context.leave(Ok(()))?;
+                Ok(None)
+            }
+            Err(e) => {
+                context.unexpected(*e)?;
+                continue;
+            }
+        };
+    }
+}
+
+/// Iterator returned by `XdfReader::items`.
+pub struct Items<'a, R: Read> {
+    reader: &'a mut XdfReader<R>,
+}
+
+impl<R: Read> Iterator for Items<'_, R> {
+    type Item = Result<XdfItem, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.reader.finished {
+            return None;
+        }
+        let item = self.reader.next_item().transpose();
+        if !matches!(item, Some(Ok(_))) {
+            self.reader.finished = true;
+        }
+        item
+    }
+}
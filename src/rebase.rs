@@ -0,0 +1,308 @@
+//! Moving a definition to another software revision of the same ECU, where the calibration data has shifted.
+//! Items are found in the target bin by searching for their data in the source bin:
+//! the z data and embedded axes of tables, and the data of constants.
+//! Blocks that are too short or too common to be distinctive are not searched for.
+//! Items that could not be found, and flags, take the shift of the nearest item that was found with good confidence.
+//!
+//! Each block is then located on its own, as axis breakpoints are often shared between maps and stored apart from their data,
+//! so they need not move with the rest of the table. Blocks that cannot be found on their own take the shift of their item.
+//!
+//! Patches and checksums are copied unchanged and should be reviewed by hand.
+
+use std::ops::Range;
+
+use crate::{
+    data_types::*,
+    ids::{items, ItemRef},
+    reader::XdfItem,
+};
+
+/// Shortest block of data that is searched for.
+const MIN_SIGNATURE: usize = 4;
+/// Blocks found more often than this in the target are not distinctive.
+const MAX_OCCURRENCES: usize = 16;
+/// Lowest confidence of an item whose shift is used for its neighbours.
+const NEIGHBOUR_CONFIDENCE: f32 = 0.5;
+
+/// How the new address of an item was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// The item's own data was found in the target
+    Signature,
+    /// Shifted like the nearest item that was found
+    Neighbour,
+    /// Block not found on its own, shifted like the item it belongs to
+    Item,
+    /// Left at its old address
+    NotFound,
+}
+
+/// New location of an item, see `XDFFormat::rebase`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relocation {
+    pub item: ItemRef,
+    /// Old address of the item, as returned by `XdfItem::address`
+    pub from: u32,
+    /// New address of the item, `None` when it was not found
+    pub to: Option<u32>,
+    /// How the shift of the item as a whole was found, this is the shift of blocks that are not found on their own
+    pub method: Method,
+    /// Between 0 and 1. For signatures this is the share of the item's data that matches, divided by the number of equally good matches.
+    /// Neighbours score between 0.25 and 0.5 depending on how much of their data matches after shifting.
+    pub confidence: f32,
+    /// Each block of the item's data with an address, in document order (the axes of tables, the data of constants and flags)
+    pub blocks: Vec<BlockRelocation>,
+}
+
+/// New location of one block of an item's data, see `Relocation::blocks`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockRelocation {
+    /// Old address of the block
+    pub from: u32,
+    /// New address of the block, `None` when neither it nor its item was found
+    pub to: Option<u32>,
+    /// `Signature` when found on its own, otherwise `Item` or `NotFound`
+    pub method: Method,
+    /// Between 0 and 1. Signatures score as for items,
+    /// blocks shifted like their item score the item's confidence times the share of the block that matches after shifting.
+    pub confidence: f32,
+}
+
+/// File offset of a block of data.
+fn block(data: &EmbeddedData, base: i64) -> Option<Range<i64>> {
+    let range = data.byte_range()?;
+    Some(range.start as i64 + base..range.end as i64 + base)
+}
+
+/// File offsets of the item's data in the order of `embedded`.
+fn blocks(item: &XdfItem, base: i64) -> Vec<Range<i64>> {
+    embedded(item)
+        .into_iter()
+        .filter_map(|d| block(d, base))
+        .collect()
+}
+
+/// Data of the item stored in the bin, in document order.
+fn embedded(item: &XdfItem) -> Vec<&EmbeddedData> {
+    match item {
+        XdfItem::Table(t) => t
+            .axis
+            .iter()
+            .filter_map(|a| a.embeddeddata.as_ref())
+            .collect(),
+        XdfItem::Constant(c) => c.embedded_data.iter().collect(),
+        XdfItem::Flag(f) => f.embedded_data.iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn embedded_mut(item: &mut XdfItem) -> Vec<&mut EmbeddedData> {
+    match item {
+        XdfItem::Table(t) => t
+            .axis
+            .iter_mut()
+            .filter_map(|a| a.embeddeddata.as_mut())
+            .collect(),
+        XdfItem::Constant(c) => c.embedded_data.iter_mut().collect(),
+        XdfItem::Flag(f) => f.embedded_data.iter_mut().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn slice<'a>(bin: &'a [u8], range: &Range<i64>) -> Option<&'a [u8]> {
+    let start = usize::try_from(range.start).ok()?;
+    let end = usize::try_from(range.end).ok()?;
+    bin.get(start..end)
+}
+
+fn shift(address: u32, delta: i64) -> Option<u32> {
+    u32::try_from(i64::from(address) + delta).ok()
+}
+
+/// Offsets of every occurrence of `needle` in `haystack`.
+fn occurrences(haystack: &[u8], needle: &[u8]) -> Vec<usize> {
+    haystack
+        .windows(needle.len())
+        .enumerate()
+        .filter(|(_, w)| *w == needle)
+        .map(|(i, _)| i)
+        .take(MAX_OCCURRENCES + 1)
+        .collect()
+}
+
+/// Number of bytes of the blocks that are the same in the target after shifting by `delta`,
+/// `None` if a block does not fit in the target.
+fn matching(source: &[u8], target: &[u8], blocks: &[Range<i64>], delta: i64) -> Option<usize> {
+    let mut matched = 0;
+    for block in blocks {
+        let old = slice(source, block)?;
+        let new = slice(target, &(block.start + delta..block.end + delta))?;
+        matched += old.iter().zip(new).filter(|(a, b)| a == b).count();
+    }
+    Some(matched)
+}
+
+/// Shift and confidence of an item found by its own data.
+fn find(source: &[u8], target: &[u8], blocks: &[Range<i64>]) -> Option<(i64, f32)> {
+    let total: usize = blocks.iter().map(|b| (b.end - b.start) as usize).sum();
+    let mut deltas: Vec<i64> = Vec::new();
+    for block in blocks {
+        let Some(data) = slice(source, block) else {
+            continue;
+        };
+        if data.len() < MIN_SIGNATURE || data.iter().all(|b| *b == data[0]) {
+            continue;
+        }
+        let found = occurrences(target, data);
+        if found.len() <= MAX_OCCURRENCES {
+            deltas.extend(found.into_iter().map(|offset| offset as i64 - block.start));
+        }
+    }
+    deltas.sort_unstable();
+    deltas.dedup();
+
+    let scored: Vec<(i64, usize)> = deltas
+        .into_iter()
+        .filter_map(|d| Some((d, matching(source, target, blocks, d)?)))
+        .collect();
+    let best = scored.iter().map(|(_, score)| *score).max()?;
+    let ties = scored.iter().filter(|(_, score)| *score == best).count();
+    // Prefer the smallest move between equally good matches
+    let (delta, _) = scored
+        .into_iter()
+        .filter(|(_, score)| *score == best)
+        .min_by_key(|(d, _)| d.abs())?;
+    Some((delta, best as f32 / total as f32 / ties as f32))
+}
+
+/// Locates each block of the item with an address on its own, falling back to the shift of the item.
+/// Blocks are only searched for when `search` is set.
+fn relocate_blocks(
+    source: &[u8],
+    target: &[u8],
+    item: &XdfItem,
+    base: i64,
+    search: bool,
+    fallback: Option<(i64, f32)>,
+) -> Vec<BlockRelocation> {
+    embedded(item)
+        .into_iter()
+        .filter_map(|data| Some((data.mmedaddress?, block(data, base))))
+        .map(|(from, block)| {
+            let blocks = Vec::from_iter(block);
+            let found = if search && !blocks.is_empty() {
+                find(source, target, &blocks)
+            } else {
+                None
+            };
+            let (delta, method, confidence) = match (found, fallback) {
+                (Some((delta, confidence)), _) => (Some(delta), Method::Signature, confidence),
+                (None, Some((delta, confidence))) => {
+                    let total: i64 = blocks.iter().map(|b| b.end - b.start).sum();
+                    let matched = matching(source, target, &blocks, delta).unwrap_or(0);
+                    let share = matched as f32 / total.max(1) as f32;
+                    (Some(delta), Method::Item, confidence * share)
+                }
+                (None, None) => (None, Method::NotFound, 0.0),
+            };
+            BlockRelocation {
+                from,
+                to: delta.and_then(|d| shift(from, d)),
+                method,
+                confidence,
+            }
+        })
+        .collect()
+}
+
+impl XDFFormat {
+    /// Finds the items of this definition, written for the `source` bin, in the `target` bin of another software revision.
+    /// Returns the relocated definition and where each item with an address was moved to.
+    pub fn rebase(&self, source: &[u8], target: &[u8]) -> (XDFFormat, Vec<Relocation>) {
+        let base = i64::from(self.header.as_ref().and_then(|h| h.baseoffset).unwrap_or(0));
+        let mut items: Vec<(ItemRef, XdfItem)> = items(self)
+            .into_iter()
+            .filter(|(_, item)| item.address().is_some() && !embedded(item).is_empty())
+            .collect();
+
+        let mut relocations: Vec<(Option<i64>, Relocation)> = items
+            .iter()
+            .map(|(reference, item)| {
+                let from = item.address().unwrap_or_default();
+                let found = match item {
+                    XdfItem::Flag(_) => None,
+                    _ => find(source, target, &blocks(item, base)),
+                };
+                let (delta, method, confidence) = match found {
+                    Some((delta, confidence)) => (Some(delta), Method::Signature, confidence),
+                    None => (None, Method::NotFound, 0.0),
+                };
+                let relocation = Relocation {
+                    item: *reference,
+                    from,
+                    to: None,
+                    method,
+                    confidence,
+                    blocks: Vec::new(),
+                };
+                (delta, relocation)
+            })
+            .collect();
+
+        let anchors: Vec<(u32, i64)> = relocations
+            .iter()
+            .filter(|(_, r)| r.confidence >= NEIGHBOUR_CONFIDENCE)
+            .filter_map(|(delta, r)| Some((r.from, (*delta)?)))
+            .collect();
+        for ((delta, relocation), (_, item)) in relocations.iter_mut().zip(&items) {
+            if delta.is_some() {
+                continue;
+            }
+            let nearest = anchors
+                .iter()
+                .min_by_key(|(from, _)| from.abs_diff(relocation.from));
+            let blocks = blocks(item, base);
+            let total: i64 = blocks.iter().map(|b| b.end - b.start).sum();
+            let Some((_, shift)) = nearest else {
+                continue;
+            };
+            let Some(matched) = matching(source, target, &blocks, *shift) else {
+                continue;
+            };
+            *delta = Some(*shift);
+            relocation.method = Method::Neighbour;
+            relocation.confidence = 0.25 + 0.25 * matched as f32 / total.max(1) as f32;
+        }
+
+        for ((delta, relocation), (_, item)) in relocations.iter_mut().zip(&mut items) {
+            let search = !matches!(item, XdfItem::Flag(_));
+            let fallback = delta.map(|d| (d, relocation.confidence));
+            relocation.blocks = relocate_blocks(source, target, item, base, search, fallback);
+            // The item's address is that of one of its blocks, which may have been found on its own
+            relocation.to = relocation
+                .blocks
+                .iter()
+                .find(|b| b.from == relocation.from)
+                .map_or_else(|| delta.and_then(|d| shift(relocation.from, d)), |b| b.to);
+            let addresses = embedded_mut(item)
+                .into_iter()
+                .filter(|data| data.mmedaddress.is_some());
+            for (data, block) in addresses.zip(&relocation.blocks) {
+                if block.to.is_some() {
+                    data.mmedaddress = block.to;
+                }
+            }
+        }
+
+        let mut rebased = self.clone();
+        for (reference, item) in items {
+            match (reference, item) {
+                (ItemRef::Table(i), XdfItem::Table(v)) => rebased.tables[i] = v,
+                (ItemRef::Constant(i), XdfItem::Constant(v)) => rebased.constants[i] = v,
+                (ItemRef::Flag(i), XdfItem::Flag(v)) => rebased.flags[i] = v,
+                _ => {}
+            }
+        }
+        (rebased, relocations.into_iter().map(|(_, r)| r).collect())
+    }
+}
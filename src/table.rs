@@ -0,0 +1,199 @@
+//! Reading a table from a bin as a grid of values with its axis breakpoints, in the units shown to the user.
+//!
+//! The z axis holds the table data: `mmedrowcount` rows of `mmedcolcount` values, row by row.
+//! The x axis runs along the columns and the y axis along the rows. Where the z data does not give a count,
+//! the `indexcount` of the matching axis is used.
+//!
+//! Axis values come from the first of these the axis has:
+//! - a link to another table (`embedinfo type="3"`): the z values of that table, with that table's math.
+//! - embedded data with an address.
+//! - `LABEL`s, taken as they are since they are already display values. Labels that are not numbers become NaN.
+//!
+//! Values from embedded data have the axis' own math applied. Linked values are already converted by the linked table,
+//! the `MATH` of a linked axis is usually a copy of that table's and is not applied again.
+//!
+//! Axes with none of these, or missing altogether, are numbered from 0.
+
+use std::fmt;
+
+use crate::{
+    bin::{BinError, BinImage},
+    data_types::*,
+    ids::link_target,
+    math::{equation, MathError},
+};
+
+/// Reason a table could not be resolved, see `XDFTable::resolve`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolveError {
+    /// Table has no z axis with embedded data
+    MissingData,
+    /// Linked axis refers to a uniqueid no table has
+    UnknownLink(u32),
+    /// Axis provides fewer values than the table has rows or columns
+    AxisLength {
+        axis: String,
+        expected: usize,
+        found: usize,
+    },
+    Bin(BinError),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingData => write!(f, "table has no z data"),
+            Self::UnknownLink(uid) => write!(f, "no table with uniqueid {uid:#X}"),
+            Self::AxisLength {
+                axis,
+                expected,
+                found,
+            } => write!(f, "axis {axis} has {found} values, expected {expected}"),
+            Self::Bin(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+impl From<BinError> for ResolveError {
+    fn from(e: BinError) -> Self {
+        Self::Bin(e)
+    }
+}
+
+impl From<MathError> for ResolveError {
+    fn from(e: MathError) -> Self {
+        Self::Bin(BinError::Math(e))
+    }
+}
+
+/// Table data and breakpoints read from a bin, see `XDFTable::resolve`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResolvedTable {
+    /// Table values by row, then column
+    pub values: Vec<Vec<f64>>,
+    /// Breakpoints of the columns
+    pub x: Vec<f64>,
+    /// Breakpoints of the rows
+    pub y: Vec<f64>,
+}
+
+impl ResolvedTable {
+    pub fn rows(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.values.first().map_or(self.x.len(), Vec::len)
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<f64> {
+        self.values.get(row)?.get(col).copied()
+    }
+}
+
+fn count(value: Option<u32>) -> Option<usize> {
+    value.filter(|v| *v > 0).map(|v| v as usize)
+}
+
+fn indices(len: usize) -> Vec<f64> {
+    (0..len).map(|i| i as f64).collect()
+}
+
+impl XDFTable {
+    /// Number of rows and columns of the table.
+    fn shape(&self) -> (usize, usize) {
+        let data = self.axis_by_id("z").and_then(|z| z.embeddeddata.as_ref());
+        let axis_count = |id| count(self.axis_by_id(id)?.count);
+        let rows = count(data.and_then(|d| d.mmedrowcount)).or_else(|| axis_count("y"));
+        let cols = count(data.and_then(|d| d.mmedcolcount)).or_else(|| axis_count("x"));
+        (rows.unwrap_or(1), cols.unwrap_or(1))
+    }
+
+    /// Z values with math applied, row by row.
+    fn z_values(&self, bin: &BinImage) -> Result<Vec<f64>, ResolveError> {
+        let z = self.axis_by_id("z").ok_or(ResolveError::MissingData)?;
+        let mut data = z.embeddeddata.clone().ok_or(ResolveError::MissingData)?;
+        let (rows, cols) = self.shape();
+        data.mmedrowcount = Some(rows as u32);
+        data.mmedcolcount = Some(cols as u32);
+        let equation = equation(z.math.as_ref())?;
+        Ok(bin
+            .read(&data)?
+            .into_iter()
+            .map(|v| equation.eval(v))
+            .collect())
+    }
+
+    /// First `len` values of an axis.
+    fn axis_values(
+        &self,
+        id: &str,
+        len: usize,
+        format: &XDFFormat,
+        bin: &BinImage,
+    ) -> Result<Vec<f64>, ResolveError> {
+        let Some(axis) = self.axis_by_id(id) else {
+            return Ok(indices(len));
+        };
+        let embedded = axis
+            .embeddeddata
+            .as_ref()
+            .filter(|d| d.mmedaddress.is_some());
+        let mut values = if let Some(target) = link_target(axis) {
+            let linked = format
+                .tables
+                .iter()
+                .find(|t| t.uid == Some(target))
+                .ok_or(ResolveError::UnknownLink(target))?;
+            linked.z_values(bin)?
+        } else if let Some(data) = embedded {
+            let mut data = data.clone();
+            if data.mmedrowcount.is_none() && data.mmedcolcount.is_none() {
+                data.mmedcolcount = Some(len as u32);
+            }
+            let equation = equation(axis.math.as_ref())?;
+            bin.read(&data)?
+                .into_iter()
+                .map(|v| equation.eval(v))
+                .collect()
+        } else if !axis.labels.is_empty() {
+            let mut labels: Vec<&Label> = axis.labels.iter().collect();
+            labels.sort_by_key(|l| l.index);
+            labels
+                .into_iter()
+                .map(|l| {
+                    let value = l.value.as_deref().and_then(|v| v.trim().parse().ok());
+                    value.unwrap_or(f64::NAN)
+                })
+                .collect()
+        } else {
+            indices(len)
+        };
+        if values.len() < len {
+            return Err(ResolveError::AxisLength {
+                axis: id.to_string(),
+                expected: len,
+                found: values.len(),
+            });
+        }
+        values.truncate(len);
+        Ok(values)
+    }
+
+    /// Reads the table and its breakpoints from `bin`, `format` is the definition holding tables that axes link to.
+    pub fn resolve(
+        &self,
+        format: &XDFFormat,
+        bin: &BinImage,
+    ) -> Result<ResolvedTable, ResolveError> {
+        let (rows, cols) = self.shape();
+        let values = self.z_values(bin)?;
+        Ok(ResolvedTable {
+            values: values.chunks(cols).map(<[f64]>::to_vec).collect(),
+            x: self.axis_values("x", cols, format, bin)?,
+            y: self.axis_values("y", rows, format, bin)?,
+        })
+    }
+}
@@ -0,0 +1,714 @@
+//! Writing of XDF documents in the layout TunerPro produces, so TunerPro can reopen them.
+//! Elements are written in the order TunerPro writes them, with the same tag casing and number formatting,
+//! e.g. `uniqueid="0x6E81"`, `mmedtypeflags="0x02"`, `<indexcount>16</indexcount>` and `<min>0.000000</min>`.
+//!
+//! Like TunerPro the output is ASCII with CRLF line endings and no XML declaration,
+//! other characters (including line breaks inside text) are written as character references such as `&#220;` and `&#013;`.
+
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    io::{self, Write},
+    mem,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{data_types::*, reader::XdfItem};
+
+/// Children of an open element, see `XmlWriter::unknown_children`.
+#[derive(Default)]
+struct Frame {
+    /// Child elements written so far
+    written: u32,
+    /// Unknown children still to be written, by position
+    unknown: VecDeque<ExtraChild>,
+}
+
+/// Streams XML to the output, indenting each element by its depth.
+struct XmlWriter<W: Write> {
+    out: W,
+    /// Encoded output of the current line
+    line: Vec<u8>,
+    depth: usize,
+    /// One for each open element
+    frames: Vec<Frame>,
+    /// The innermost start tag has been written without its closing `>`, so it can still become self closing
+    pending: bool,
+    /// Written at the start of every line, before the indentation for the depth
+    prefix: String,
+    newline: &'static str,
+}
+
+impl<W: Write> XmlWriter<W> {
+    fn new(out: W) -> Self {
+        Self {
+            out,
+            line: Vec::new(),
+            depth: 0,
+            frames: Vec::new(),
+            pending: false,
+            prefix: String::new(),
+            newline: "\r\n",
+        }
+    }
+
+    /// Writes escaped text, markup characters (and quotes when `quote` is set) become entities,
+    /// control characters and anything outside ASCII become character references.
+    fn push(&mut self, text: &str, quote: bool) {
+        for c in text.chars() {
+            match c {
+                '&' => self.line.extend_from_slice(b"&amp;"),
+                '<' => self.line.extend_from_slice(b"&lt;"),
+                '>' => self.line.extend_from_slice(b"&gt;"),
+                '"' if quote => self.line.extend_from_slice(b"&quot;"),
+                ' '..='~' => self.line.push(c as u8),
+                c => write!(self.line, "&#{:03};", c as u32).expect("writing to a Vec"),
+            }
+        }
+    }
+
+    /// Writes markup that needs no escaping.
+    fn push_raw(&mut self, markup: &str) {
+        self.line.extend_from_slice(markup.as_bytes());
+    }
+
+    fn indent(&mut self) {
+        self.line.extend_from_slice(self.prefix.as_bytes());
+        for _ in 0..self.depth {
+            self.line.extend_from_slice(b"  ");
+        }
+    }
+
+    fn end_line(&mut self) -> io::Result<()> {
+        self.line.extend_from_slice(self.newline.as_bytes());
+        self.out.write_all(&self.line)?;
+        self.line.clear();
+        Ok(())
+    }
+
+    /// Finishes the start tag of the parent element, as it has content.
+    fn open_parent(&mut self) -> io::Result<()> {
+        if self.pending {
+            self.pending = false;
+            self.push_raw(">");
+            self.end_line()?;
+        }
+        Ok(())
+    }
+
+    fn attributes(&mut self, known: &[(&str, Option<String>)], extra: &[(String, String)]) {
+        let known = known
+            .iter()
+            .filter_map(|(name, value)| Some((*name, value.as_deref()?)));
+        let extra = extra
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()));
+        for (name, value) in known.chain(extra) {
+            self.push_raw(" ");
+            self.push(name, true);
+            self.push_raw("=\"");
+            self.push(value, true);
+            self.push_raw("\"");
+        }
+    }
+
+    /// Queues unknown children of the innermost element, each is written once as many children as its position have been written.
+    /// Any left when the element ends are written last.
+    fn unknown_children(&mut self, children: &[ExtraChild]) {
+        let frame = self
+            .frames
+            .last_mut()
+            .expect("unknown children outside of an element");
+        let mut children = children.to_vec();
+        children.sort_by_key(|c| c.position);
+        frame.unknown = children.into();
+    }
+
+    /// Writes the unknown children of the innermost element that come before its next child.
+    fn place_unknown(&mut self) -> io::Result<()> {
+        let Some(frame) = self.frames.last_mut() else {
+            return Ok(());
+        };
+        let mut due = Vec::new();
+        while let Some(child) = frame.unknown.front() {
+            if child.position > frame.written + due.len() as u32 {
+                break;
+            }
+            due.extend(frame.unknown.pop_front());
+        }
+        due.iter()
+            .try_for_each(|child| child.element.write_xml(self))
+    }
+
+    /// Counts a child of the innermost element as written.
+    fn child_written(&mut self) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.written += 1;
+        }
+    }
+
+    /// Starts an element, attributes with no value are left out, unknown attributes in `extra` are written last.
+    fn start(
+        &mut self,
+        name: &str,
+        known: &[(&str, Option<String>)],
+        extra: &[(String, String)],
+    ) -> io::Result<()> {
+        self.place_unknown()?;
+        self.open_parent()?;
+        self.indent();
+        self.push_raw("<");
+        self.push_raw(name);
+        self.attributes(known, extra);
+        self.pending = true;
+        self.depth += 1;
+        self.frames.push(Frame::default());
+        Ok(())
+    }
+
+    /// Closes the innermost element, elements without content are written as `<NAME ... />`.
+    fn end(&mut self, name: &str) -> io::Result<()> {
+        if let Some(frame) = self.frames.last_mut() {
+            let rest = mem::take(&mut frame.unknown);
+            rest.iter()
+                .try_for_each(|child| child.element.write_xml(self))?;
+        }
+        self.frames.pop();
+        self.child_written();
+        self.depth -= 1;
+        if self.pending {
+            self.pending = false;
+            self.push_raw(" />");
+        } else {
+            self.indent();
+            self.push_raw("</");
+            self.push_raw(name);
+            self.push_raw(">");
+        }
+        self.end_line()
+    }
+
+    /// Writes an element with no children.
+    fn empty(&mut self, name: &str, known: &[(&str, Option<String>)]) -> io::Result<()> {
+        self.start(name, known, &[])?;
+        self.end(name)
+    }
+
+    /// Writes an element that only contains text, e.g. `<title>TVUB</title>`.
+    fn text_element(
+        &mut self,
+        name: &str,
+        extra: &[(String, String)],
+        text: &str,
+    ) -> io::Result<()> {
+        self.place_unknown()?;
+        self.open_parent()?;
+        self.indent();
+        self.push_raw("<");
+        self.push_raw(name);
+        self.attributes(&[], extra);
+        self.push_raw(">");
+        self.push(text, false);
+        self.push_raw("</");
+        self.push_raw(name);
+        self.push_raw(">");
+        self.child_written();
+        self.end_line()
+    }
+
+    /// Writes a text element if the value is present.
+    fn text(&mut self, name: &str, value: Option<impl Display>) -> io::Result<()> {
+        match value {
+            Some(value) => self.text_element(name, &[], &value.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    fn comment(&mut self, text: &str) -> io::Result<()> {
+        self.push_raw("<!-- ");
+        self.push(text, false);
+        self.push_raw(" -->");
+        self.end_line()
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Item that can be written as an XDF element.
+trait WriteXml {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()>;
+}
+
+impl<T: WriteXml> WriteXml for [T] {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        self.iter().try_for_each(|item| item.write_xml(w))
+    }
+}
+
+impl<T: WriteXml> WriteXml for Option<T> {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        match self {
+            Some(item) => item.write_xml(w),
+            None => Ok(()),
+        }
+    }
+}
+
+fn dec(value: impl Display) -> String {
+    value.to_string()
+}
+
+fn hex(value: u32) -> String {
+    format!("0x{value:X}")
+}
+
+/// Bytes as pairs of hex digits without a prefix, as in XDFPATCHENTRY data.
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02X}")).collect()
+}
+
+/// Formats a float with six decimal places like TunerPro does,
+/// unless that loses precision, in which case the shortest exact representation is used.
+fn float(value: f32) -> String {
+    let fixed = format!("{value:.6}");
+    if fixed.parse::<f32>() == Ok(value) {
+        fixed
+    } else {
+        value.to_string()
+    }
+}
+
+/// Current UTC time in the format of TunerPro's `Written` comment, e.g. `02/02/2015 10:48:01`.
+fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (days, time) = (secs / 86400, secs % 86400);
+    // Civil date from days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!(
+        "{month:02}/{day:02}/{year} {:02}:{:02}:{:02}",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+impl WriteXml for RawElement {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        if let [RawNode::Text(text)] = self.children.as_slice() {
+            return w.text_element(&self.name, &self.attributes, text);
+        }
+        w.start(&self.name, &[], &self.attributes)?;
+        for child in &self.children {
+            match child {
+                RawNode::Element(e) => e.write_xml(w)?,
+                RawNode::Text(text) => {
+                    w.open_parent()?;
+                    w.indent();
+                    w.push(text, false);
+                    w.end_line()?;
+                }
+            }
+        }
+        w.end(&self.name)
+    }
+}
+
+impl WriteXml for CategoryMem {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.empty(
+            "CATEGORYMEM",
+            &[
+                ("index", self.index.map(dec)),
+                ("category", self.category.map(dec)),
+            ],
+        )
+    }
+}
+
+impl WriteXml for Category {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.empty(
+            "CATEGORY",
+            &[("index", self.index.map(hex)), ("name", self.name.clone())],
+        )
+    }
+}
+
+impl WriteXml for Defaults {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.empty(
+            "DEFAULTS",
+            &[
+                ("datasizeinbits", self.datasizeinbits.map(dec)),
+                ("sigdigits", self.sigdigits.map(dec)),
+                ("outputtype", self.outputtype.map(dec)),
+                ("signed", self.signed.map(dec)),
+                ("lsbfirst", self.lsbfirst.map(dec)),
+                ("float", self.float.map(dec)),
+            ],
+        )
+    }
+}
+
+impl WriteXml for Region {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.empty(
+            "REGION",
+            &[
+                ("type", self.rtype.map(hex)),
+                ("startaddress", self.startaddress.map(hex)),
+                ("size", self.size.map(hex)),
+                ("regionflags", self.regionflags.map(hex)),
+            ],
+        )
+    }
+}
+
+impl WriteXml for XDFHeader {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.start("XDFHEADER", &[], &self.extras.attributes)?;
+        w.unknown_children(&self.extras.children);
+        w.text("flags", self.flags.map(hex))?;
+        w.text("fileversion", self.fileversion.as_ref())?;
+        w.text("deftitle", self.deftitle.as_ref())?;
+        w.text("description", self.description.as_ref())?;
+        w.text("author", self.author.as_ref())?;
+        w.text("baseoffset", self.baseoffset)?;
+        self.defaults.write_xml(w)?;
+        self.region.write_xml(w)?;
+        self.category.write_xml(w)?;
+        w.end("XDFHEADER")
+    }
+}
+
+impl WriteXml for EmbeddedData {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.start(
+            "EMBEDDEDDATA",
+            &[
+                (
+                    "mmedtypeflags",
+                    self.mmedtypeflags.map(|v| format!("0x{v:02X}")),
+                ),
+                ("mmedaddress", self.mmedaddress.map(hex)),
+                ("mmedelementsizebits", self.mmedelementsizebits.map(dec)),
+                ("mmedrowcount", self.mmedrowcount.map(dec)),
+                ("mmedcolcount", self.mmedcolcount.map(dec)),
+                ("mmedmajorstridebits", self.mmedmajorstridebits.map(dec)),
+                ("mmedminorstridebits", self.mmedminorstridebits.map(dec)),
+            ],
+            &self.extras.attributes,
+        )?;
+        w.unknown_children(&self.extras.children);
+        w.end("EMBEDDEDDATA")
+    }
+}
+
+impl WriteXml for EmbedInfo {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.empty(
+            "embedinfo",
+            &[
+                ("type", self.etype.map(dec)),
+                ("linkobjid", self.linkobjid.map(hex)),
+            ],
+        )
+    }
+}
+
+impl WriteXml for Label {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.empty(
+            "LABEL",
+            &[
+                ("index", self.index.map(dec)),
+                ("value", self.value.clone()),
+            ],
+        )
+    }
+}
+
+impl WriteXml for Math {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.start("MATH", &[("equation", self.expression.clone())], &[])?;
+        for var in &self.vars {
+            w.empty("VAR", &[("id", Some(var.clone()))])?;
+        }
+        w.end("MATH")
+    }
+}
+
+/// Writes a `DALINK` element if the index is present.
+fn dalink<W: Write>(w: &mut XmlWriter<W>, index: Option<u32>) -> io::Result<()> {
+    match index {
+        Some(index) => w.empty("DALINK", &[("index", Some(dec(index)))]),
+        None => Ok(()),
+    }
+}
+
+impl WriteXml for XDFAxis {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.start(
+            "XDFAXIS",
+            &[("id", self.id.clone()), ("uniqueid", self.uid.map(hex))],
+            &self.extras.attributes,
+        )?;
+        w.unknown_children(&self.extras.children);
+        self.embeddeddata.write_xml(w)?;
+        w.text("units", self.unit.as_ref())?;
+        w.text("indexcount", self.count)?;
+        w.text("decimalpl", self.decimalplaces)?;
+        w.text("min", self.min.map(float))?;
+        w.text("max", self.max.map(float))?;
+        w.text("outputtype", self.outputtype)?;
+        self.embedinfo.write_xml(w)?;
+        w.text("datatype", self.datatype)?;
+        w.text("unittype", self.unittype)?;
+        dalink(w, self.dalink_index)?;
+        self.labels.write_xml(w)?;
+        self.math.write_xml(w)?;
+        w.end("XDFAXIS")
+    }
+}
+
+impl WriteXml for XDFTable {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.start(
+            "XDFTABLE",
+            &[
+                ("uniqueid", self.uid.map(hex)),
+                ("flags", self.flags.map(hex)),
+            ],
+            &self.extras.attributes,
+        )?;
+        w.unknown_children(&self.extras.children);
+        w.text("title", self.title.as_ref())?;
+        w.text("description", self.description.as_ref())?;
+        self.catmem.write_xml(w)?;
+        self.axis.write_xml(w)?;
+        w.end("XDFTABLE")
+    }
+}
+
+impl WriteXml for XDFConstant {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.start(
+            "XDFCONSTANT",
+            &[("uniqueid", self.uid.map(hex))],
+            &self.extras.attributes,
+        )?;
+        w.unknown_children(&self.extras.children);
+        w.text("title", self.title.as_ref())?;
+        w.text("description", self.description.as_ref())?;
+        self.catmem.write_xml(w)?;
+        self.embedded_data.write_xml(w)?;
+        w.text("units", self.unit.as_ref())?;
+        w.text("decimalpl", self.decimalplaces)?;
+        w.text("outputtype", self.outputtype)?;
+        w.text("datatype", self.datatype)?;
+        w.text("unittype", self.unittype)?;
+        dalink(w, self.dalink_index)?;
+        self.math.write_xml(w)?;
+        w.end("XDFCONSTANT")
+    }
+}
+
+impl WriteXml for XDFPatchEntry {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.empty(
+            "XDFPATCHENTRY",
+            &[
+                ("name", self.name.clone()),
+                ("address", self.address.map(hex)),
+                ("datasize", self.datasize.map(hex)),
+                ("patchdata", self.patchdata.as_deref().map(hex_bytes)),
+                ("basedata", self.basedata.as_deref().map(hex_bytes)),
+            ],
+        )
+    }
+}
+
+impl WriteXml for XDFPatch {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.start("XDFPATCH", &[("uniqueid", self.uid.map(hex))], &[])?;
+        w.text("title", self.title.as_ref())?;
+        w.text("description", self.description.as_ref())?;
+        self.catmem.write_xml(w)?;
+        self.entries.write_xml(w)?;
+        w.end("XDFPATCH")
+    }
+}
+
+impl WriteXml for XDFFlag {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.start("XDFFLAG", &[("uniqueid", self.uid.map(hex))], &[])?;
+        w.text("title", self.title.as_ref())?;
+        w.text("description", self.description.as_ref())?;
+        self.catmem.write_xml(w)?;
+        self.embedded_data.write_xml(w)?;
+        w.text("mask", self.mask.map(hex))?;
+        w.end("XDFFLAG")
+    }
+}
+
+impl WriteXml for ChecksumRegion {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.start("REGION", &[], &[])?;
+        w.text("datastart", self.datastart.map(hex))?;
+        w.text("dataend", self.dataend.map(hex))?;
+        w.text("datasizebits", self.datasizebits.map(hex))?;
+        w.text("storeaddress", self.storeaddress.map(hex))?;
+        w.text("calculationmethod", self.calculationmethod.map(hex))?;
+        w.end("REGION")
+    }
+}
+
+impl WriteXml for XDFChecksum {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.start("XDFCHECKSUM", &[("uniqueid", self.uid.map(hex))], &[])?;
+        w.text("title", self.title.as_ref())?;
+        w.text("description", self.description.as_ref())?;
+        self.catmem.write_xml(w)?;
+        self.regions.write_xml(w)?;
+        w.end("XDFCHECKSUM")
+    }
+}
+
+impl WriteXml for XDFFormat {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        w.start("XDFFORMAT", &[("version", self.version.clone())], &[])?;
+        self.header.write_xml(w)?;
+        self.tables.write_xml(w)?;
+        self.constants.write_xml(w)?;
+        self.patches.write_xml(w)?;
+        self.flags.write_xml(w)?;
+        self.checksums.write_xml(w)?;
+        w.end("XDFFORMAT")
+    }
+}
+
+impl WriteXml for XdfItem {
+    fn write_xml<W: Write>(&self, w: &mut XmlWriter<W>) -> io::Result<()> {
+        match self {
+            XdfItem::Header(v) => v.write_xml(w),
+            XdfItem::Table(v) => v.write_xml(w),
+            XdfItem::Constant(v) => v.write_xml(w),
+            XdfItem::Patch(v) => v.write_xml(w),
+            XdfItem::Flag(v) => v.write_xml(w),
+            XdfItem::Checksum(v) => v.write_xml(w),
+        }
+    }
+}
+
+/// Writes a single item for insertion into an existing document, used by the format preserving editor.
+/// Lines after the first are prefixed with `indent`, the output has no leading indentation or trailing line break.
+pub(crate) fn item_xml(item: &XdfItem, indent: &str, newline: &'static str) -> Vec<u8> {
+    let mut w = XmlWriter::new(Vec::new());
+    w.prefix = indent.to_string();
+    w.newline = newline;
+    item.write_xml(&mut w).expect("writing to a Vec");
+    let out = w.out;
+    out[indent.len()..out.len() - newline.len()].to_vec()
+}
+
+/// Controls how a document is written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Write a canonical form, so documents with equal content produce identical bytes, e.g. for version control.
+    /// Items are sorted by uniqueid, categories, category memberships and labels by index and unknown attributes by name.
+    /// The `Written` timestamp comment is left out.
+    pub canonical: bool,
+}
+
+/// Sorts items by uniqueid, items without one go last, falling back to the title.
+fn sort_items<T>(items: &mut [T], key: fn(&T) -> (Option<u32>, &Option<String>)) {
+    items.sort_by(|a, b| {
+        let (a, b) = (key(a), key(b));
+        (a.0.is_none(), a).cmp(&(b.0.is_none(), b))
+    });
+}
+
+fn sort_extras(extras: &mut Extras) {
+    extras.attributes.sort_by(|a, b| a.0.cmp(&b.0));
+}
+
+fn sort_embedded(data: &mut Option<EmbeddedData>) {
+    if let Some(data) = data {
+        sort_extras(&mut data.extras);
+    }
+}
+
+/// Copy of the document in the order written by `WriteOptions { canonical: true }`.
+fn canonical(format: &XDFFormat) -> XDFFormat {
+    let mut format = format.clone();
+    if let Some(header) = &mut format.header {
+        header.category.sort_by_key(|c| c.index);
+        sort_extras(&mut header.extras);
+    }
+    sort_items(&mut format.tables, |t| (t.uid, &t.title));
+    for table in &mut format.tables {
+        table.catmem.sort_by_key(|c| (c.index, c.category));
+        sort_extras(&mut table.extras);
+        for axis in &mut table.axis {
+            axis.labels.sort_by_key(|l| l.index);
+            sort_embedded(&mut axis.embeddeddata);
+            sort_extras(&mut axis.extras);
+        }
+    }
+    sort_items(&mut format.constants, |t| (t.uid, &t.title));
+    for constant in &mut format.constants {
+        constant.catmem.sort_by_key(|c| (c.index, c.category));
+        sort_embedded(&mut constant.embedded_data);
+        sort_extras(&mut constant.extras);
+    }
+    sort_items(&mut format.patches, |t| (t.uid, &t.title));
+    for patch in &mut format.patches {
+        patch.catmem.sort_by_key(|c| (c.index, c.category));
+    }
+    sort_items(&mut format.flags, |t| (t.uid, &t.title));
+    for flag in &mut format.flags {
+        flag.catmem.sort_by_key(|c| (c.index, c.category));
+        sort_embedded(&mut flag.embedded_data);
+    }
+    sort_items(&mut format.checksums, |t| (t.uid, &t.title));
+    for checksum in &mut format.checksums {
+        checksum.catmem.sort_by_key(|c| (c.index, c.category));
+    }
+    format
+}
+
+impl XDFFormat {
+    /// Writes the document as an XDF file that TunerPro can open, headed by a `Written` comment with the current time.
+    /// Items are written in the order header, tables, constants, patches, flags, checksums.
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.write_to_with_options(writer, WriteOptions::default())
+    }
+
+    /// Same as `write_to`, but with control over the layout of the output.
+    pub fn write_to_with_options<W: Write>(
+        &self,
+        writer: W,
+        options: WriteOptions,
+    ) -> io::Result<()> {
+        let mut w = XmlWriter::new(writer);
+        if options.canonical {
+            canonical(self).write_xml(&mut w)?;
+        } else {
+            w.comment(&format!("Written {}", timestamp()))?;
+            self.write_xml(&mut w)?;
+        }
+        w.finish()
+    }
+}
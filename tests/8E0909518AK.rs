@@ -1,8 +1,8 @@
-#![feature(if_let_guard)]
-#![feature(decl_macro)]
-
 use std::fs::File;
-use xdftuneparser::parse_buffer;
+use xdftuneparser::{
+    data_types::{XDFElement, XDFFormat},
+    parse_buffer,
+};
 
 /// needs to be broken out/improved/verified.
 #[test]
@@ -10,3 +10,17 @@ fn parse_amb_xdf() {
     let file = File::open("tests/8E0909518AK_368072_NEF_STG_1v7.xdf").unwrap();
     parse_buffer(file).unwrap().unwrap();
 }
+
+#[test]
+fn parse_amb_xdf_typed() {
+    let format = XDFFormat::from_path("tests/8E0909518AK_368072_NEF_STG_1v7.xdf").unwrap();
+    assert_eq!(format.version.as_deref(), Some("1.50"));
+    assert_eq!(format.tables.len(), 43);
+    assert_eq!(format.constants.len(), 11);
+
+    let file = File::open("tests/8E0909518AK_368072_NEF_STG_1v7.xdf").unwrap();
+    assert_eq!(
+        parse_buffer(file).unwrap().unwrap(),
+        XDFElement::XDFFormat(format)
+    );
+}
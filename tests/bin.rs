@@ -0,0 +1,344 @@
+use xdftuneparser::{
+    bin::{BinError, BinImage},
+    data_types::*,
+};
+
+const AMB_XDF: &str = "tests/8E0909518AK_368072_NEF_STG_1v7.xdf";
+
+fn data(address: u32, bits: u32, flags: Option<u32>, count: u32) -> EmbeddedData {
+    EmbeddedData {
+        mmedtypeflags: flags,
+        mmedaddress: Some(address),
+        mmedelementsizebits: Some(bits),
+        mmedcolcount: Some(count),
+        ..Default::default()
+    }
+}
+
+fn header(base: u32, defaults: Defaults) -> XDFHeader {
+    XDFHeader {
+        baseoffset: Some(base),
+        defaults: Some(defaults),
+        region: Some(Region {
+            startaddress: Some(0x0),
+            size: Some(0x20),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn elements_follow_type_flags() {
+    let bin = BinImage::new(vec![0x12, 0x34, 0xFF, 0xFE, 0x80, 0x00, 0x00, 0x01]);
+
+    assert_eq!(
+        bin.read(&data(0, 8, None, 3)).unwrap(),
+        vec![18.0, 52.0, 255.0]
+    );
+    assert_eq!(
+        bin.read(&data(0, 16, None, 2)).unwrap(),
+        vec![4660.0, 65534.0]
+    );
+    assert_eq!(
+        bin.read(&data(0, 16, Some(0x02), 2)).unwrap(),
+        vec![13330.0, 65279.0]
+    );
+    assert_eq!(
+        bin.read(&data(2, 16, Some(0x01), 2)).unwrap(),
+        vec![-2.0, -32768.0]
+    );
+    assert_eq!(bin.read(&data(2, 8, Some(0x03), 1)).unwrap(), vec![-1.0]);
+    assert_eq!(
+        bin.read(&data(0, 32, None, 2)).unwrap(),
+        vec![305_463_294.0, 2_147_483_649.0]
+    );
+    assert_eq!(
+        bin.read(&data(4, 32, Some(0x03), 1)).unwrap(),
+        vec![16_777_344.0]
+    );
+}
+
+#[test]
+fn header_defaults_and_base_offset() {
+    let mut contents = vec![0; 0x40];
+    contents[0x30..0x34].copy_from_slice(&[0xFE, 0xFF, 0x02, 0x00]);
+    let defaults = Defaults {
+        datasizeinbits: Some(16),
+        signed: Some(1),
+        lsbfirst: Some(1),
+        ..Default::default()
+    };
+    let bin = BinImage::new(contents).with_header(&header(0x20, defaults));
+
+    let values = EmbeddedData {
+        mmedaddress: Some(0x10),
+        mmedcolcount: Some(2),
+        ..Default::default()
+    };
+    assert_eq!(bin.read(&values).unwrap(), vec![-2.0, 2.0]);
+    // Item flags take precedence over the defaults
+    assert_eq!(
+        bin.read(&data(0x10, 16, Some(0), 1)).unwrap(),
+        vec![65279.0]
+    );
+}
+
+#[test]
+fn addresses_are_checked() {
+    let bin = BinImage::new(vec![0; 0x30]).with_header(&header(0x8, Defaults::default()));
+
+    assert_eq!(bin.read(&data(0x1E, 16, None, 1)).unwrap(), vec![0.0]);
+    assert_eq!(
+        bin.read(&data(0x1F, 16, None, 1)),
+        Err(BinError::OutsideRegion(0x1F))
+    );
+    assert_eq!(
+        bin.read(&data(0x1C, 8, None, 8)),
+        Err(BinError::OutsideRegion(0x20))
+    );
+    assert_eq!(
+        BinImage::new(vec![0; 4]).read(&data(0x2, 32, None, 1)),
+        Err(BinError::OutOfBounds(0x2))
+    );
+    assert_eq!(
+        bin.read(&data(0x0, 24, None, 1)),
+        Err(BinError::ElementSize(24))
+    );
+    assert_eq!(
+        bin.read(&EmbeddedData::default()),
+        Err(BinError::MissingAddress)
+    );
+}
+
+#[test]
+fn values_are_written() {
+    let mut bin = BinImage::new(vec![0; 8]);
+    bin.write(&data(0, 16, Some(0x03), 2), &[-2.0, 0x1234 as f64])
+        .unwrap();
+    bin.write(&data(4, 32, None, 1), &[0xDEADBEEF_u32 as f64])
+        .unwrap();
+    assert_eq!(
+        bin.as_bytes(),
+        &[0xFE, 0xFF, 0x34, 0x12, 0xDE, 0xAD, 0xBE, 0xEF]
+    );
+
+    let before = bin.clone();
+    assert_eq!(
+        bin.write(&data(0, 8, None, 2), &[1.0, 256.0]),
+        Err(BinError::OutOfRange {
+            value: 256.0,
+            min: 0.0,
+            max: 255.0
+        })
+    );
+    assert!(matches!(
+        bin.write(&data(0, 8, Some(0x01), 1), &[0.5]),
+        Err(BinError::OutOfRange { .. })
+    ));
+    assert_eq!(
+        bin.write(&data(0, 8, None, 2), &[1.0]),
+        Err(BinError::Count {
+            expected: 2,
+            found: 1
+        })
+    );
+    assert_eq!(bin, before);
+}
+
+#[test]
+fn bundled_items_can_be_read() {
+    let format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let mut contents = vec![0; 0x100000];
+    // TVUB, five little endian words
+    contents[0x14DAE..0x14DB8].copy_from_slice(&[1, 0, 2, 0, 3, 0, 0, 1, 0xFF, 0xFF]);
+    let bin = BinImage::new(contents).with_format(&format);
+
+    let tvub = format
+        .tables
+        .iter()
+        .find(|t| t.uid == Some(0x14DAE))
+        .unwrap();
+    let z = tvub.axis_by_id("z").unwrap().embeddeddata.as_ref().unwrap();
+    assert_eq!(bin.read(z).unwrap(), vec![1.0, 2.0, 3.0, 256.0, 65535.0]);
+}
+
+/// 8 bit data of `rows` by `cols` at address 0.
+fn grid(rows: u32, cols: u32, flags: u32, major: i32, minor: i32) -> EmbeddedData {
+    EmbeddedData {
+        mmedtypeflags: Some(flags),
+        mmedaddress: Some(0),
+        mmedelementsizebits: Some(8),
+        mmedrowcount: Some(rows),
+        mmedcolcount: Some(cols),
+        mmedmajorstridebits: Some(major),
+        mmedminorstridebits: Some(minor),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn layouts() {
+    let bin = BinImage::new((0..32).collect());
+    let read = |data: EmbeddedData| bin.read(&data).unwrap();
+
+    // Packed rows, strides of 0 or less are the defaults
+    assert_eq!(
+        read(grid(2, 3, 0, 0, 0)),
+        vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]
+    );
+    assert_eq!(
+        read(grid(2, 3, 0, -32, -32)),
+        vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]
+    );
+    // Packed columns, still returned row by row
+    assert_eq!(
+        read(grid(2, 3, 0x04, 0, 0)),
+        vec![0.0, 2.0, 4.0, 1.0, 3.0, 5.0]
+    );
+    // Rows padded to 4 bytes
+    assert_eq!(
+        read(grid(2, 3, 0, 32, 0)),
+        vec![0.0, 1.0, 2.0, 4.0, 5.0, 6.0]
+    );
+    // Two maps interleaved element by element
+    assert_eq!(
+        read(grid(2, 3, 0, 0, 16)),
+        vec![0.0, 2.0, 4.0, 6.0, 8.0, 10.0]
+    );
+    assert_eq!(
+        read(grid(2, 3, 0, 64, 16)),
+        vec![0.0, 2.0, 4.0, 8.0, 10.0, 12.0]
+    );
+    // Padded and interleaved columns
+    assert_eq!(
+        read(grid(2, 3, 0x04, 32, 16)),
+        vec![0.0, 4.0, 8.0, 2.0, 6.0, 10.0]
+    );
+    assert_eq!(
+        read(grid(2, 3, 0x04, 0, 24)),
+        vec![0.0, 6.0, 12.0, 3.0, 9.0, 15.0]
+    );
+
+    // A single row or column is a line per element, spaced by the major stride
+    for flags in [0, 0x04] {
+        assert_eq!(read(grid(1, 4, flags, 0, 0)), vec![0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(read(grid(4, 1, flags, 0, 0)), vec![0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(read(grid(1, 4, flags, 24, 0)), vec![0.0, 3.0, 6.0, 9.0]);
+        assert_eq!(read(grid(4, 1, flags, 0, 16)), vec![0.0, 2.0, 4.0, 6.0]);
+    }
+
+    // Strides apply to 16 bit elements as well
+    let mut words = grid(2, 2, 0x04, 64, 0);
+    words.mmedelementsizebits = Some(16);
+    assert_eq!(read(words), vec![1.0, 2057.0, 515.0, 2571.0]);
+
+    assert_eq!(bin.read(&grid(2, 2, 0, 12, 0)), Err(BinError::Stride(12)));
+    assert_eq!(bin.read(&grid(2, 2, 0, 0, 4)), Err(BinError::Stride(4)));
+}
+
+#[test]
+fn layouts_are_written_and_spanned() {
+    let mut bin = BinImage::new(vec![0; 12]);
+    let data = grid(2, 3, 0x04, 32, 0);
+    bin.write(&data, &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+    assert_eq!(bin.as_bytes(), &[1, 4, 0, 0, 2, 5, 0, 0, 3, 6, 0, 0]);
+    assert_eq!(bin.read(&data).unwrap(), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+    // Gaps are spanned, but padding after the last element is not
+    assert_eq!(data.byte_range(), Some(0..10));
+    assert_eq!(grid(2, 3, 0, 0, 16).byte_range(), Some(0..11));
+    assert_eq!(grid(2, 3, 0, 0, 0).byte_range(), Some(0..6));
+    assert_eq!(grid(2, 3, 0, 0, 4).byte_range(), None);
+    assert_eq!(
+        bin.write(&grid(2, 3, 0, 96, 0), &[0.0; 6]),
+        Err(BinError::OutOfBounds(12))
+    );
+}
+
+#[test]
+fn float_elements() {
+    let mut contents = vec![0; 24];
+    contents[0..4].copy_from_slice(&1.5f32.to_be_bytes());
+    contents[4..8].copy_from_slice(&(-0.25f32).to_le_bytes());
+    contents[8..16].copy_from_slice(&1e-3f64.to_be_bytes());
+    contents[16..24].copy_from_slice(&(-2e10f64).to_le_bytes());
+    let mut bin = BinImage::new(contents);
+
+    assert_eq!(bin.read(&data(0, 32, Some(0x10000), 1)).unwrap(), vec![1.5]);
+    assert_eq!(
+        bin.read(&data(4, 32, Some(0x10002), 1)).unwrap(),
+        vec![-0.25]
+    );
+    // The signed flag makes no difference to floats
+    assert_eq!(
+        bin.read(&data(4, 32, Some(0x10003), 1)).unwrap(),
+        vec![-0.25]
+    );
+    assert_eq!(
+        bin.read(&data(8, 64, Some(0x10000), 1)).unwrap(),
+        vec![1e-3]
+    );
+    assert_eq!(
+        bin.read(&data(16, 64, Some(0x10002), 1)).unwrap(),
+        vec![-2e10]
+    );
+
+    bin.write(&data(0, 32, Some(0x10002), 2), &[0.1, 3e38])
+        .unwrap();
+    assert_eq!(&bin.as_bytes()[0..4], &0.1f32.to_le_bytes());
+    assert_eq!(
+        bin.read(&data(4, 32, Some(0x10002), 1)).unwrap(),
+        vec![f64::from(3e38f32)]
+    );
+    bin.write(&data(8, 64, Some(0x10000), 2), &[0.1, -1e300])
+        .unwrap();
+    assert_eq!(
+        bin.read(&data(8, 64, Some(0x10000), 2)).unwrap(),
+        vec![0.1, -1e300]
+    );
+    assert!(matches!(
+        bin.write(&data(0, 32, Some(0x10000), 1), &[1e39]),
+        Err(BinError::OutOfRange { .. })
+    ));
+    assert!(matches!(
+        bin.write(&data(0, 64, Some(0x10000), 1), &[f64::NAN]),
+        Err(BinError::OutOfRange { .. })
+    ));
+    assert_eq!(
+        bin.read(&data(0, 16, Some(0x10000), 1)),
+        Err(BinError::ElementSize(16))
+    );
+    assert_eq!(
+        bin.read(&data(0, 64, Some(0), 1)),
+        Err(BinError::ElementSize(64))
+    );
+    assert_eq!(
+        BinError::ElementSize(64).to_string(),
+        "unsupported element size of 64 bits"
+    );
+}
+
+#[test]
+fn float_defaults() {
+    let mut contents = vec![0; 0x30];
+    contents[0x10..0x14].copy_from_slice(&2.5f32.to_le_bytes());
+    let defaults = Defaults {
+        datasizeinbits: Some(32),
+        lsbfirst: Some(1),
+        float: Some(1),
+        ..Default::default()
+    };
+    let bin = BinImage::new(contents).with_header(&header(0x0, defaults));
+
+    let value = EmbeddedData {
+        mmedaddress: Some(0x10),
+        ..Default::default()
+    };
+    assert_eq!(bin.read(&value).unwrap(), vec![2.5]);
+    // Flags replace the defaults altogether
+    assert_eq!(
+        bin.read(&data(0x10, 32, Some(0x02), 1)).unwrap(),
+        vec![f64::from(2.5f32.to_bits())]
+    );
+}
@@ -0,0 +1,165 @@
+use xdftuneparser::{
+    builder::{AxisBuilder, BuildError, ConstantBuilder, TableBuilder},
+    data_types::*,
+    math::MathError,
+};
+
+const AMB_XDF: &str = "tests/8E0909518AK_368072_NEF_STG_1v7.xdf";
+
+#[test]
+fn build_matches_tunerpro_table() {
+    let tvub = TableBuilder::new("TVUB")
+        .uid(0x14DAE)
+        .rows(5)
+        .address(0x14DAE)
+        .element_bits(16)
+        .type_flags(0x02)
+        .equation("0.000000+X*0.002667")
+        .decimal_places(2)
+        .min(0.0)
+        .max(255.0)
+        .output_type(1)
+        .x_axis(AxisBuilder::labels(["0.00"]))
+        .y_axis(AxisBuilder::linked(0x14DA9))
+        .build()
+        .unwrap();
+
+    let format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let expected = format.tables.iter().find(|t| t.uid == Some(0x14DAE));
+    assert_eq!(Some(&tvub), expected);
+}
+
+#[test]
+fn build_matches_tunerpro_constant() {
+    let cdtes = ConstantBuilder::new("CDTES")
+        .uid(0x3BFE)
+        .description("Codeword: turn off tank venting diagnosis (EURO-Coding), CD..=0 ->no Dia")
+        .category(27)
+        .address(0x181B2)
+        .build()
+        .unwrap();
+
+    let format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let expected = format.constants.iter().find(|c| c.uid == Some(0x3BFE));
+    assert_eq!(Some(&cdtes), expected);
+}
+
+#[test]
+fn axes_are_generated() {
+    let table = TableBuilder::new("KFMIRL")
+        .rows(16)
+        .cols(12)
+        .address(0x1EF9E)
+        .element_bits(16)
+        .equation("X*0.023438")
+        .x_axis(
+            AxisBuilder::embedded(0x1EF7E, 16)
+                .units("RPM")
+                .equation("0.25*X"),
+        )
+        .build()
+        .unwrap();
+
+    let [x, y, z] = table.axis.as_slice() else {
+        panic!("expected three axes");
+    };
+    assert_eq!(x.count, Some(12));
+    assert_eq!(x.embedinfo.as_ref().unwrap().etype, Some(1));
+    let x_data = x.embeddeddata.as_ref().unwrap();
+    assert_eq!(
+        (x_data.mmedaddress, x_data.mmedcolcount),
+        (Some(0x1EF7E), Some(12))
+    );
+
+    assert_eq!(y.count, Some(16));
+    assert_eq!(y.labels.len(), 16);
+    assert_eq!(y.labels[15].value.as_deref(), Some("15"));
+
+    let z_data = z.embeddeddata.as_ref().unwrap();
+    assert_eq!(
+        (z_data.mmedrowcount, z_data.mmedcolcount),
+        (Some(16), Some(12))
+    );
+    assert_eq!(z.math.as_ref().unwrap().vars, vec!["X".to_string()]);
+
+    // Embedded y axes count their breakpoints as columns too, as TunerPro writes them
+    let table = TableBuilder::new("KFMIRL")
+        .rows(16)
+        .cols(12)
+        .address(0x1EF9E)
+        .y_axis(AxisBuilder::embedded(0x1EF5E, 16))
+        .build()
+        .unwrap();
+    let y_data = table.axis[1].embeddeddata.as_ref().unwrap();
+    assert_eq!(
+        (y_data.mmedaddress, y_data.mmedrowcount, y_data.mmedcolcount),
+        (Some(0x1EF5E), None, Some(16))
+    );
+}
+
+#[test]
+fn dimensions_follow_labels() {
+    let table = TableBuilder::new("Gear")
+        .address(0x100)
+        .x_axis(AxisBuilder::labels(1..=6))
+        .build()
+        .unwrap();
+    assert_eq!(table.axis[0].count, Some(6));
+    assert_eq!(table.axis[1].count, Some(1));
+}
+
+#[test]
+fn inconsistent_tables_are_rejected() {
+    let table = || TableBuilder::new("T").uid(0x10).address(0x100);
+
+    assert_eq!(
+        table().cols(4).x_axis(AxisBuilder::labels(0..3)).build(),
+        Err(BuildError::LabelCount {
+            axis: "x".into(),
+            expected: 4,
+            found: 3
+        })
+    );
+    assert_eq!(table().rows(0).build(), Err(BuildError::EmptyTable));
+    assert_eq!(
+        table().element_bits(12).build(),
+        Err(BuildError::ElementSize(12))
+    );
+    assert_eq!(
+        table().y_axis(AxisBuilder::embedded(0x200, 24)).build(),
+        Err(BuildError::ElementSize(24))
+    );
+    assert_eq!(
+        table().y_axis(AxisBuilder::linked(0x10)).build(),
+        Err(BuildError::SelfLink(0x10))
+    );
+    assert_eq!(
+        table().address(0xFFFF_FFF0).rows(16).cols(16).build(),
+        Err(BuildError::AddressOverflow)
+    );
+    assert_eq!(
+        table().equation(" ").build(),
+        Err(BuildError::EmptyEquation)
+    );
+    assert_eq!(
+        table().equation("X*(0.5").build(),
+        Err(BuildError::Equation(MathError::Syntax(6)))
+    );
+    assert_eq!(
+        ConstantBuilder::new("C")
+            .address(0x100)
+            .equation("X*FACTOR")
+            .build(),
+        Err(BuildError::Equation(MathError::UnknownName(
+            "FACTOR".into()
+        )))
+    );
+    assert_eq!(
+        TableBuilder::new("T").build(),
+        Err(BuildError::MissingAddress)
+    );
+    assert_eq!(
+        ConstantBuilder::new("C").element_bits(32).build(),
+        Err(BuildError::MissingAddress)
+    );
+}
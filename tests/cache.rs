@@ -0,0 +1,91 @@
+use std::{fs, io::ErrorKind, path::PathBuf};
+
+use xdftuneparser::{cache::snapshot_path, data_types::*};
+
+const AMB_XDF: &str = "tests/8E0909518AK_368072_NEF_STG_1v7.xdf";
+
+/// Copy of the bundled XDF in a directory of its own.
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("xdftuneparser-{}-{name}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("definition.xdf");
+    fs::copy(AMB_XDF, &path).unwrap();
+    let _ = fs::remove_file(snapshot_path(&path));
+    path
+}
+
+#[test]
+fn snapshot_round_trips() {
+    let source = fs::read(AMB_XDF).unwrap();
+    let format = XDFFormat::parse(source.as_slice()).unwrap();
+    let mut snapshot = Vec::new();
+    format.write_snapshot(&source, &mut snapshot).unwrap();
+
+    assert!(snapshot.len() < source.len() / 4);
+    let reread = XDFFormat::read_snapshot(&source, snapshot.as_slice()).unwrap();
+    assert_eq!(reread, Some(format));
+}
+
+#[test]
+fn stale_snapshots_are_ignored() {
+    let source = fs::read(AMB_XDF).unwrap();
+    let format = XDFFormat::parse(source.as_slice()).unwrap();
+    let mut snapshot = Vec::new();
+    format.write_snapshot(&source, &mut snapshot).unwrap();
+
+    let mut edited = source.clone();
+    let at = edited.len() - 20;
+    edited[at] ^= 0x20;
+    assert_eq!(
+        XDFFormat::read_snapshot(&edited, snapshot.as_slice()).unwrap(),
+        None
+    );
+
+    // Written by another version of the layout
+    let mut other_layout = snapshot.clone();
+    other_layout[4] ^= 1;
+    assert_eq!(
+        XDFFormat::read_snapshot(&source, other_layout.as_slice()).unwrap(),
+        None
+    );
+
+    let err = XDFFormat::read_snapshot(&source, &snapshot[..snapshot.len() - 1]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    let err = XDFFormat::read_snapshot(&source, &source[..]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn snapshot_is_kept_next_to_the_xdf() {
+    let path = scratch("cached");
+    let snapshot = snapshot_path(&path);
+    assert_eq!(snapshot.file_name().unwrap(), "definition.xdf.snapshot");
+
+    let format = XDFFormat::from_path_cached(&path).unwrap();
+    assert_eq!(format, XDFFormat::from_path(AMB_XDF).unwrap());
+    assert!(snapshot.exists());
+
+    // An up to date snapshot is used instead of the XDF
+    let source = fs::read(&path).unwrap();
+    let mut marked = format.clone();
+    marked.version = Some("snapshot".into());
+    marked
+        .write_snapshot(&source, fs::File::create(&snapshot).unwrap())
+        .unwrap();
+    assert_eq!(XDFFormat::from_path_cached(&path).unwrap(), marked);
+
+    // Editing the XDF invalidates it
+    let edited = String::from_utf8(source)
+        .unwrap()
+        .replace("TVUB_AXIS", "TVUB_AXES");
+    fs::write(&path, edited).unwrap();
+    let reloaded = XDFFormat::from_path_cached(&path).unwrap();
+    assert_eq!(reloaded.version.as_deref(), Some("1.50"));
+    assert!(reloaded
+        .tables
+        .iter()
+        .any(|t| t.title.as_deref() == Some("TVUB_AXES")));
+    assert_eq!(XDFFormat::from_path_cached(&path).unwrap(), reloaded);
+
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,117 @@
+use xdftuneparser::{
+    bin::{BinError, BinImage},
+    data_types::*,
+};
+
+const AMB_XDF: &str = "tests/8E0909518AK_368072_NEF_STG_1v7.xdf";
+
+fn constant(title: &str) -> XDFConstant {
+    let format = XDFFormat::from_path(AMB_XDF).unwrap();
+    format
+        .constants
+        .into_iter()
+        .find(|c| c.title.as_deref() == Some(title))
+        .unwrap()
+}
+
+fn scalar(expression: &str, bits: u32, flags: u32) -> XDFConstant {
+    XDFConstant {
+        embedded_data: Some(EmbeddedData {
+            mmedtypeflags: Some(flags),
+            mmedaddress: Some(0x2),
+            mmedelementsizebits: Some(bits),
+            ..Default::default()
+        }),
+        math: Some(Math {
+            vars: vec!["X".to_string()],
+            expression: Some(expression.to_string()),
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn codeword_read_and_write() {
+    let cdtes = constant("CDTES");
+    let mut bin = BinImage::new(vec![0; 0x20000]);
+    assert_eq!(cdtes.read(&bin).unwrap(), 0.0);
+
+    cdtes.write(&mut bin, 1.0).unwrap();
+    assert_eq!(bin.as_bytes()[0x181B2], 1);
+    assert_eq!(cdtes.read(&bin).unwrap(), 1.0);
+    assert!(matches!(
+        cdtes.write(&mut bin, 256.0),
+        Err(BinError::OutOfRange { .. })
+    ));
+    assert_eq!(bin.as_bytes()[0x181B2], 1);
+}
+
+#[test]
+fn limits_are_scaled_and_rounded() {
+    // 16 bit little endian, 0.75 per bit with an offset of -48
+    let limit = scalar("(X*0.75)-48", 16, 0x02);
+    let mut bin = BinImage::new(vec![0; 8]);
+
+    limit.write(&mut bin, 27.0).unwrap();
+    assert_eq!(&bin.as_bytes()[2..4], &[100, 0]);
+    assert_eq!(limit.read(&bin).unwrap(), 27.0);
+
+    // Not a multiple of the scale, stored as the nearest value
+    limit.write(&mut bin, 27.4).unwrap();
+    assert_eq!(limit.read(&bin).unwrap(), 27.75);
+
+    assert!(matches!(
+        limit.write(&mut bin, -49.0),
+        Err(BinError::OutOfRange { .. })
+    ));
+    let signed = scalar("(X*0.75)-48", 16, 0x03);
+    signed.write(&mut bin, -49.5).unwrap();
+    assert_eq!(&bin.as_bytes()[2..4], &[0xFE, 0xFF]);
+    assert_eq!(signed.read(&bin).unwrap(), -49.5);
+
+    let squared = scalar("X*X", 8, 0);
+    assert_eq!(squared.read(&bin).unwrap(), 254.0 * 254.0);
+    assert!(matches!(
+        squared.write(&mut bin, 4.0),
+        Err(BinError::Math(_))
+    ));
+    assert_eq!(
+        XDFConstant::default().read(&bin),
+        Err(BinError::MissingAddress)
+    );
+}
+
+#[test]
+fn values_are_formatted_for_display() {
+    let mut limit = scalar("X", 8, 0);
+    assert_eq!(limit.format(27.456), "27.46");
+    limit.decimalplaces = Some(1);
+    limit.unit = Some("°C".to_string());
+    assert_eq!(limit.format(27.456), "27.5 °C");
+    limit.outputtype = Some(2);
+    assert_eq!(limit.format(27.456), "27 °C");
+    limit.unit = None;
+    limit.outputtype = Some(3);
+    assert_eq!(limit.format(255.0), "0xFF");
+    limit.outputtype = Some(4);
+    assert_eq!(limit.format(f64::from(0x4142)), "AB");
+
+    assert_eq!(
+        constant("(KVB) Constant for fuel consumption display").format(6.09375),
+        "6.09 cm^3/min"
+    );
+}
+
+#[test]
+fn float_constants_are_not_rounded() {
+    let mut pressure = scalar("X*0.001", 32, 0x10002);
+    pressure.embedded_data.as_mut().unwrap().mmedaddress = Some(0x0);
+    let mut bin = BinImage::new(vec![0; 4]);
+
+    pressure.write(&mut bin, 1.2345).unwrap();
+    assert_eq!(bin.as_bytes(), &1234.5f32.to_le_bytes());
+    assert_eq!(pressure.read(&bin).unwrap(), 1.2345);
+
+    pressure.write(&mut bin, 0.1).unwrap();
+    assert_eq!(bin.as_bytes(), &100f32.to_le_bytes());
+}
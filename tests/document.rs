@@ -0,0 +1,166 @@
+use xdftuneparser::{
+    data_types::*, document::XdfDocument, encoding::Encoding, error::ErrorKind,
+    parser::ParseOptions, reader::XdfItem,
+};
+
+const AMB_XDF: &str = "tests/8E0909518AK_368072_NEF_STG_1v7.xdf";
+
+const DOC: &str = "<?xml version=\"1.0\"?>
+<!-- Written 02/02/2015 10:48:01 -->
+<XDFFORMAT version=\"1.50\">
+    <!-- hand maintained, keep the odd indentation -->
+    <XDFCONSTANT uniqueid=\"0x3BFE\">
+        <title>CDTES</title>
+        <EMBEDDEDDATA mmedaddress=\"0x181B2\" mmedelementsizebits=\"8\" />
+    </XDFCONSTANT>
+
+    <XDFCONSTANT uniqueid=\"0x3BFF\"><title>Other</title><!-- why not --></XDFCONSTANT>
+    <colorscheme name=\"dark\" />
+</XDFFORMAT>
+";
+
+const LENIENT: ParseOptions = ParseOptions {
+    strict: false,
+    encoding: Encoding::Auto,
+};
+
+fn write(document: &XdfDocument) -> String {
+    let mut out = Vec::new();
+    document.write_to(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+fn constant(uid: u32, title: &str) -> XdfItem {
+    XdfItem::Constant(XDFConstant {
+        uid: Some(uid),
+        title: Some(title.into()),
+        ..Default::default()
+    })
+}
+
+#[test]
+fn unchanged_document_is_identical() {
+    let document = XdfDocument::from_path(AMB_XDF).unwrap();
+    assert!(!document.is_modified());
+    assert_eq!(write(&document), std::fs::read_to_string(AMB_XDF).unwrap());
+    assert_eq!(document.to_format(), XDFFormat::from_path(AMB_XDF).unwrap());
+}
+
+#[test]
+fn only_the_edited_line_changes() {
+    let mut document = XdfDocument::from_path(AMB_XDF).unwrap();
+    let table = document
+        .tables_mut()
+        .find(|t| t.uid == Some(0x1055))
+        .unwrap();
+    table.axis[0].math.as_mut().unwrap().expression = Some("0.500000 * X".into());
+    assert!(document.is_modified());
+
+    let source = std::fs::read_to_string(AMB_XDF).unwrap();
+    let written = write(&document);
+    let changed: Vec<_> = source
+        .lines()
+        .zip(written.lines())
+        .filter(|(a, b)| a != b)
+        .collect();
+    assert_eq!(source.lines().count(), written.lines().count());
+    assert_eq!(
+        changed,
+        vec![(
+            "      <MATH equation=\"0.250000 * X\">",
+            "      <MATH equation=\"0.500000 * X\">"
+        )]
+    );
+}
+
+#[test]
+fn comments_and_unknown_content_are_kept() {
+    let mut document = XdfDocument::with_options(DOC.as_bytes().to_vec(), LENIENT).unwrap();
+    assert_eq!(document.len(), 2);
+    assert_eq!(document.version(), Some("1.50"));
+    assert_eq!(document.warnings().len(), 1);
+
+    document.constants_mut().nth(1).unwrap().unit = Some("-".into());
+    assert_eq!(
+        write(&document),
+        DOC.replace(
+            "<XDFCONSTANT uniqueid=\"0x3BFF\"><title>Other</title><!-- why not --></XDFCONSTANT>",
+            "<XDFCONSTANT uniqueid=\"0x3BFF\">
+      <title>Other</title>
+      <units>-</units>
+    </XDFCONSTANT>"
+        )
+    );
+}
+
+#[test]
+fn items_can_be_added_and_removed() {
+    let mut document = XdfDocument::with_options(DOC.as_bytes().to_vec(), LENIENT).unwrap();
+    let removed = document.remove(0);
+    assert!(matches!(removed, XdfItem::Constant(c) if c.uid == Some(0x3BFE)));
+    document.push(constant(0x4000, "New"));
+
+    let written = write(&document);
+    assert_eq!(
+        written,
+        "<?xml version=\"1.0\"?>
+<!-- Written 02/02/2015 10:48:01 -->
+<XDFFORMAT version=\"1.50\">
+    <!-- hand maintained, keep the odd indentation -->
+
+    <XDFCONSTANT uniqueid=\"0x3BFF\"><title>Other</title><!-- why not --></XDFCONSTANT>
+    <colorscheme name=\"dark\" />
+    <XDFCONSTANT uniqueid=\"0x4000\">
+      <title>New</title>
+    </XDFCONSTANT>
+</XDFFORMAT>
+"
+    );
+    let reread = XdfDocument::with_options(written.into_bytes(), LENIENT).unwrap();
+    assert_eq!(
+        reread.items().collect::<Vec<_>>(),
+        document.items().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn items_can_be_added_to_an_empty_root() {
+    let mut document = XdfDocument::new(b"<XDFFORMAT version=\"1.50\"/>\r\n".to_vec()).unwrap();
+    assert!(document.is_empty());
+    document.push(constant(0x1, "First"));
+
+    assert_eq!(
+        write(&document),
+        "<XDFFORMAT version=\"1.50\">\r
+  <XDFCONSTANT uniqueid=\"0x1\">\r
+    <title>First</title>\r
+  </XDFCONSTANT>\r
+</XDFFORMAT>\r
+"
+    );
+}
+
+#[test]
+fn utf16_is_rejected() {
+    let source: Vec<u8> = "<XDFFORMAT />"
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    let err = XdfDocument::new(source).unwrap_err();
+    assert!(matches!(err.kind, ErrorKind::Io(e) if e.kind() == std::io::ErrorKind::InvalidData));
+}
+
+#[test]
+fn declared_encoding_applies_to_items() {
+    let source = b"<?xml version=\"1.0\" encoding=\"windows-1252\"?>
+<XDFFORMAT version=\"1.50\">
+  <XDFCONSTANT uniqueid=\"0x1\"><title>\xC3\xBC</title></XDFCONSTANT>
+</XDFFORMAT>
+";
+    let document = XdfDocument::new(source.to_vec()).unwrap();
+    let Some(XdfItem::Constant(constant)) = document.get(0) else {
+        panic!("expected a constant");
+    };
+    assert_eq!(constant.title.as_deref(), Some("Ã¼"));
+    assert!(!document.is_modified());
+}
@@ -0,0 +1,189 @@
+use xdftuneparser::{data_types::*, parse_buffer};
+
+/// Wraps item definitions in a minimal XDFFORMAT document and parses it.
+fn parse_items(items: &str) -> XDFFormat {
+    let doc = format!("<XDFFORMAT version=\"1.60\">\n{items}\n</XDFFORMAT>");
+    match parse_buffer(doc.as_bytes()).unwrap().unwrap() {
+        XDFElement::XDFFormat(format) => format,
+        e => panic!("expected XDFFORMAT, got {e:?}"),
+    }
+}
+
+#[test]
+fn parse_patch() {
+    let format = parse_items(
+        r#"  <XDFPATCH uniqueid="0x2A1">
+    <title>Disable rear O2 sensors</title>
+    <description>Patches out the post-cat lambda check</description>
+    <CATEGORYMEM index="0" category="3" />
+    <XDFPATCHENTRY name="Jump" address="0x1A2B4" datasize="0x2" patchdata="0DFB" basedata="3DF7" />
+    <XDFPATCHENTRY name="Flag" address="0x1A300" datasize="0x1" patchdata="ff" basedata="00" />
+  </XDFPATCH>"#,
+    );
+
+    assert_eq!(
+        format.patches,
+        vec![XDFPatch {
+            title: Some("Disable rear O2 sensors".into()),
+            description: Some("Patches out the post-cat lambda check".into()),
+            catmem: vec![CategoryMem {
+                index: Some(0),
+                category: Some(3),
+            }],
+            uid: Some(0x2A1),
+            entries: vec![
+                XDFPatchEntry {
+                    name: Some("Jump".into()),
+                    address: Some(0x1A2B4),
+                    datasize: Some(2),
+                    patchdata: Some(vec![0x0D, 0xFB]),
+                    basedata: Some(vec![0x3D, 0xF7]),
+                },
+                XDFPatchEntry {
+                    name: Some("Flag".into()),
+                    address: Some(0x1A300),
+                    datasize: Some(1),
+                    patchdata: Some(vec![0xFF]),
+                    basedata: Some(vec![0x00]),
+                },
+            ],
+        }]
+    );
+}
+
+#[test]
+fn parse_flag() {
+    let format = parse_items(
+        r#"  <XDFFLAG uniqueid="0x1A3C">
+    <title>CDTES</title>
+    <description>Codeword: turn off tank venting diagnosis</description>
+    <CATEGORYMEM index="0" category="27" />
+    <EMBEDDEDDATA mmedaddress="0x181B2" mmedelementsizebits="8" mmedmajorstridebits="0" mmedminorstridebits="0" />
+    <mask>0x4</mask>
+  </XDFFLAG>"#,
+    );
+
+    assert_eq!(
+        format.flags,
+        vec![XDFFlag {
+            title: Some("CDTES".into()),
+            description: Some("Codeword: turn off tank venting diagnosis".into()),
+            catmem: vec![CategoryMem {
+                index: Some(0),
+                category: Some(27),
+            }],
+            uid: Some(0x1A3C),
+            embedded_data: Some(EmbeddedData {
+                mmedaddress: Some(0x181B2),
+                mmedelementsizebits: Some(8),
+                mmedmajorstridebits: Some(0),
+                mmedminorstridebits: Some(0),
+                ..Default::default()
+            }),
+            mask: Some(0x4),
+        }]
+    );
+}
+
+#[test]
+fn parse_checksum() {
+    let format = parse_items(
+        r#"  <XDFCHECKSUM uniqueid="0x5A0">
+    <title>Main checksum</title>
+    <REGION>
+      <datastart>0x10000</datastart>
+      <dataend>0x1FFFB</dataend>
+      <datasizebits>0x10</datasizebits>
+      <storeaddress>0x1FFFC</storeaddress>
+      <calculationmethod>0x1</calculationmethod>
+    </REGION>
+  </XDFCHECKSUM>"#,
+    );
+
+    assert_eq!(
+        format.checksums,
+        vec![XDFChecksum {
+            title: Some("Main checksum".into()),
+            uid: Some(0x5A0),
+            regions: vec![ChecksumRegion {
+                datastart: Some(0x10000),
+                dataend: Some(0x1FFFB),
+                datasizebits: Some(16),
+                storeaddress: Some(0x1FFFC),
+                calculationmethod: Some(1),
+            }],
+            ..Default::default()
+        }]
+    );
+}
+
+#[test]
+fn region_kind_follows_parent() {
+    let format = parse_items(
+        r#"  <XDFHEADER>
+    <REGION />
+  </XDFHEADER>
+  <XDFCHECKSUM uniqueid="0x5A0">
+    <REGION type="0x1">
+      <datastart>0x10000</datastart>
+    </REGION>
+  </XDFCHECKSUM>"#,
+    );
+
+    assert_eq!(format.header.unwrap().region, Some(Region::default()));
+    assert_eq!(
+        format.checksums[0].regions,
+        vec![ChecksumRegion {
+            datastart: Some(0x10000),
+            ..Default::default()
+        }]
+    );
+}
+
+#[test]
+fn parse_number_forms() {
+    let format = parse_items(
+        r#"  <XDFTABLE uniqueid="0X6e81" flags=" 0x0 ">
+    <XDFAXIS id="x">
+      <EMBEDDEDDATA mmedtypeflags="0X02" mmedaddress=" 0x1EF9E" mmedelementsizebits="16.000000" mmedmajorstridebits="-0x20" mmedminorstridebits="-0" />
+      <indexcount> 16 </indexcount>
+      <min>-0x10</min>
+      <max>2.55e2</max>
+    </XDFAXIS>
+  </XDFTABLE>"#,
+    );
+
+    let table = &format.tables[0];
+    assert_eq!(table.uid, Some(0x6E81));
+    let axis = &table.axis[0];
+    assert_eq!(
+        axis.embeddeddata,
+        Some(EmbeddedData {
+            mmedaddress: Some(0x1EF9E),
+            mmedelementsizebits: Some(16),
+            mmedmajorstridebits: Some(-32),
+            mmedminorstridebits: Some(0),
+            mmedtypeflags: Some(2),
+            ..Default::default()
+        })
+    );
+    assert_eq!(axis.count, Some(16));
+    assert_eq!(axis.min, Some(-16.0));
+    assert_eq!(axis.max, Some(255.0));
+}
+
+#[test]
+fn table_flags_as_attribute_or_element() {
+    let format = parse_items(
+        r#"  <XDFTABLE uniqueid="0x1" flags="0x30" />
+  <XDFTABLE uniqueid="0x2">
+    <flags>0x30</flags>
+  </XDFTABLE>
+  <XDFTABLE uniqueid="0x3" flags="0x1">
+    <flags>0x30</flags>
+  </XDFTABLE>"#,
+    );
+
+    let flags: Vec<_> = format.tables.iter().map(|t| t.flags).collect();
+    assert_eq!(flags, vec![Some(0x30), Some(0x30), Some(0x1)]);
+}
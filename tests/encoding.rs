@@ -0,0 +1,123 @@
+use std::io::Read;
+
+use xdftuneparser::{
+    data_types::*, encoding::Encoding, parse_buffer, parse_buffer_with_options,
+    parser::ParseOptions,
+};
+
+/// Builds a document containing a single constant with the given (already encoded) description.
+fn doc(prefix: &[u8], description: &[u8]) -> Vec<u8> {
+    let mut doc = prefix.to_vec();
+    doc.extend_from_slice(
+        b"<XDFFORMAT version=\"1.50\">\n  <XDFCONSTANT uniqueid=\"0x1\">\n    <description>",
+    );
+    doc.extend_from_slice(description);
+    doc.extend_from_slice(b"</description>\n  </XDFCONSTANT>\n</XDFFORMAT>\n");
+    doc
+}
+
+fn description(element: XDFElement) -> String {
+    let XDFElement::XDFFormat(format) = element else {
+        panic!("expected XDFFORMAT");
+    };
+    format.constants[0].description.clone().unwrap()
+}
+
+fn parse_as(bytes: &[u8], encoding: Encoding) -> String {
+    let options = ParseOptions {
+        encoding,
+        ..Default::default()
+    };
+    description(parse_buffer_with_options(bytes, options).unwrap().0)
+}
+
+/// Reader that returns a single byte per call, to split multi byte sequences between reads.
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.0.split_first() {
+            Some((first, rest)) if !buf.is_empty() => {
+                buf[0] = *first;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn windows_1252_is_detected() {
+    // "Drehzahl 20°C, Öl µs €" in Windows-1252
+    let bytes = doc(b"", b"Drehzahl 20\xB0C, \xD6l \xB5s \x80");
+    let expected = "Drehzahl 20°C, Öl µs €";
+    assert_eq!(
+        description(parse_buffer(bytes.as_slice()).unwrap().unwrap()),
+        expected
+    );
+    assert_eq!(parse_as(&bytes, Encoding::Windows1252), expected);
+}
+
+#[test]
+fn declared_encoding_is_followed() {
+    let bytes = doc(
+        b"<?xml version=\"1.0\" encoding=\"windows-1252\"?>\n",
+        b"Z\xFCndwinkel",
+    );
+    assert_eq!(parse_as(&bytes, Encoding::Auto), "Zündwinkel");
+
+    // Valid UTF-8 as well, only the declaration tells them apart
+    let ambiguous = b"\xC3\xBC";
+    let declared = doc(
+        b"<?xml version='1.0' encoding='Windows-1252'?>\n",
+        ambiguous,
+    );
+    assert_eq!(parse_as(&declared, Encoding::Auto), "Ã¼");
+    assert_eq!(parse_as(&doc(b"", ambiguous), Encoding::Auto), "ü");
+    let (element, _) =
+        parse_buffer_with_options(Trickle(&declared), ParseOptions::default()).unwrap();
+    assert_eq!(description(element), "Ã¼");
+
+    let latin1 = doc(b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>", b"\x80");
+    assert_eq!(parse_as(&latin1, Encoding::Auto), "\u{80}");
+}
+
+#[test]
+fn utf8_is_kept() {
+    let text = "Zündwinkel 20°C €";
+    let bytes = doc(b"\xEF\xBB\xBF", text.as_bytes());
+    assert_eq!(parse_as(&bytes, Encoding::Auto), text);
+    assert_eq!(parse_as(&bytes, Encoding::Utf8), text);
+
+    let options = ParseOptions::default();
+    let (element, _) = parse_buffer_with_options(Trickle(&bytes), options).unwrap();
+    assert_eq!(description(element), text);
+}
+
+#[test]
+fn utf16_is_detected() {
+    let text = "Zündwinkel 20°C 𝛼";
+    let utf8 = doc(b"", text.as_bytes());
+    let utf8 = std::str::from_utf8(&utf8).unwrap();
+
+    let mut le = vec![0xFF, 0xFE];
+    let mut be = vec![0xFE, 0xFF];
+    for unit in utf8.encode_utf16() {
+        le.extend_from_slice(&unit.to_le_bytes());
+        be.extend_from_slice(&unit.to_be_bytes());
+    }
+    assert_eq!(parse_as(&le, Encoding::Auto), text);
+    assert_eq!(parse_as(&be, Encoding::Auto), text);
+    assert_eq!(parse_as(&le[2..], Encoding::Auto), text);
+    assert_eq!(parse_as(&be[2..], Encoding::Utf16Be), text);
+
+    let (element, _) = parse_buffer_with_options(Trickle(&le), ParseOptions::default()).unwrap();
+    assert_eq!(description(element), text);
+}
+
+#[test]
+fn latin1() {
+    let bytes = doc(b"", b"\xB5s \x80");
+    assert_eq!(parse_as(&bytes, Encoding::Iso8859_1), "µs \u{80}");
+}
@@ -0,0 +1,127 @@
+use xdftuneparser::{
+    data_types::XDFFormat,
+    error::{Error, ErrorKind},
+    parse_buffer,
+};
+
+fn parse_err(doc: &str) -> Error {
+    parse_buffer(doc.as_bytes()).unwrap().unwrap_err()
+}
+
+#[test]
+fn bad_attribute_is_located() {
+    let err = parse_err(
+        r#"<XDFFORMAT version="1.50">
+  <XDFTABLE uniqueid="0x6E81" flags="0x0">
+    <XDFAXIS id="z">
+      <DALINK index="first" />
+    </XDFAXIS>
+  </XDFTABLE>
+</XDFFORMAT>"#,
+    );
+
+    assert_eq!(err.kind, ErrorKind::BadValue("first".into()));
+    assert_eq!(err.attribute.as_deref(), Some("index"));
+    assert_eq!(
+        err.path.as_deref(),
+        Some("XDFFORMAT/XDFTABLE[uid=0x6E81]/XDFAXIS[z]/DALINK")
+    );
+    let position = err.position.unwrap();
+    assert_eq!((position.row, position.column), (3, 6));
+    assert_eq!(
+        err.to_string(),
+        "4:7: XDFFORMAT/XDFTABLE[uid=0x6E81]/XDFAXIS[z]/DALINK: bad value `first` in attribute `index`"
+    );
+}
+
+#[test]
+fn unknown_element_is_located() {
+    let err = parse_err(
+        r#"<XDFFORMAT version="1.50">
+  <XDFCONSTANT uniqueid="0x3BFE">
+    <frobnicate />
+  </XDFCONSTANT>
+</XDFFORMAT>"#,
+    );
+
+    assert_eq!(err.kind, ErrorKind::UnknownType("frobnicate".into()));
+    assert_eq!(
+        err.path.as_deref(),
+        Some("XDFFORMAT/XDFCONSTANT[uid=0x3BFE]/frobnicate")
+    );
+}
+
+#[test]
+fn bad_number_is_an_error() {
+    let err = parse_err(
+        r#"<XDFFORMAT version="1.50">
+  <XDFCONSTANT uniqueid="0x3BFE">
+    <EMBEDDEDDATA mmedaddress="0x181G2" mmedelementsizebits="8" />
+  </XDFCONSTANT>
+</XDFFORMAT>"#,
+    );
+
+    assert_eq!(err.kind, ErrorKind::BadValue("0x181G2".into()));
+    assert_eq!(err.attribute.as_deref(), Some("mmedaddress"));
+    assert_eq!(
+        err.path.as_deref(),
+        Some("XDFFORMAT/XDFCONSTANT[uid=0x3BFE]/EMBEDDEDDATA")
+    );
+}
+
+#[test]
+fn bad_element_text_is_an_error() {
+    let err = parse_err(
+        r#"<XDFFORMAT version="1.50">
+  <XDFTABLE uniqueid="0x1">
+    <XDFAXIS id="y">
+      <indexcount>lots</indexcount>
+    </XDFAXIS>
+  </XDFTABLE>
+</XDFFORMAT>"#,
+    );
+
+    assert_eq!(err.kind, ErrorKind::BadValue("lots".into()));
+    assert_eq!(err.attribute, None);
+    assert_eq!(
+        err.path.as_deref(),
+        Some("XDFFORMAT/XDFTABLE[uid=0x1]/XDFAXIS[y]/indexcount")
+    );
+}
+
+#[test]
+fn root_must_be_xdfformat() {
+    let err = "<XDFTABLE uniqueid=\"0x1\" />"
+        .parse::<XDFFormat>()
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnexpectedRoot("XDFTABLE".into()));
+}
+
+#[test]
+fn io_errors_are_folded() {
+    let err = XDFFormat::from_path("tests/does_not_exist.xdf").unwrap_err();
+    assert!(matches!(&err.kind, ErrorKind::Io(e) if e.kind() == std::io::ErrorKind::NotFound));
+}
+
+#[test]
+fn repeated_signs_are_bad_values() {
+    for value in ["0x-5", "0x+5", "--5", "-+5", "+-5", "-0x-5", "--5.0", "0x"] {
+        let doc = format!(
+            r#"<XDFFORMAT version="1.50">
+  <XDFCONSTANT uniqueid="0x3BFE">
+    <EMBEDDEDDATA mmedaddress="0x10" mmedmajorstridebits="{value}" />
+  </XDFCONSTANT>
+</XDFFORMAT>"#
+        );
+        let err = parse_err(&doc);
+        assert_eq!(err.kind, ErrorKind::BadValue(value.into()), "{value}");
+    }
+    let doc = r#"<XDFFORMAT version="1.50">
+  <XDFTABLE uniqueid="0x1">
+    <XDFAXIS id="z">
+      <min>--1</min>
+    </XDFAXIS>
+  </XDFTABLE>
+</XDFFORMAT>"#;
+    assert_eq!(parse_err(doc).kind, ErrorKind::BadValue("--1".into()));
+}
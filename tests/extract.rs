@@ -0,0 +1,98 @@
+use regex::Regex;
+use xdftuneparser::{data_types::*, extract::Filter};
+
+const AMB_XDF: &str = "tests/8E0909518AK_368072_NEF_STG_1v7.xdf";
+
+fn titles<'a>(tables: impl IntoIterator<Item = &'a XDFTable>) -> Vec<&'a str> {
+    tables
+        .into_iter()
+        .filter_map(|t| t.title.as_deref())
+        .collect()
+}
+
+fn axes() -> Vec<Category> {
+    vec![Category {
+        index: Some(0),
+        name: Some("Axes".into()),
+    }]
+}
+
+#[test]
+fn extract_by_category() {
+    let format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let extracted = format.extract(&Filter::new().category("Axes"));
+
+    assert_eq!(
+        titles(&extracted.tables),
+        vec![
+            "LAMFA_ROWAXIS",
+            "SNM16GKUB",
+            "LAMFA_COLAXIS",
+            "SRL12GKUB",
+            "SNM16ZUUB",
+            "TVUB_AXIS"
+        ]
+    );
+    assert!(extracted.constants.is_empty());
+    assert_eq!(extracted.header.as_ref().unwrap().category, axes());
+    for table in &extracted.tables {
+        assert_eq!(
+            table.catmem,
+            vec![CategoryMem {
+                index: Some(0),
+                category: Some(0)
+            }]
+        );
+    }
+}
+
+#[test]
+fn linked_tables_are_pulled_in() {
+    let format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let extracted = format.extract(&Filter::new().title(Regex::new("^TVUB$").unwrap()));
+
+    assert_eq!(titles(&extracted.tables), vec!["TVUB", "TVUB_AXIS"]);
+    assert_eq!(extracted.header.as_ref().unwrap().category, axes());
+    let ids = extracted.ids();
+    assert_eq!(ids.links().len(), 1);
+    assert_eq!(ids.dangling_links().count(), 0);
+}
+
+#[test]
+fn extract_by_address() {
+    let format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let extracted = format.extract(&Filter::new().addresses(0x181B2..0x181B3));
+
+    assert!(extracted.tables.is_empty());
+    let [cdtes] = extracted.constants.as_slice() else {
+        panic!("expected a single constant");
+    };
+    assert_eq!(cdtes.title.as_deref(), Some("CDTES"));
+    // Category 27 is not declared in the header
+    assert!(cdtes.catmem.is_empty());
+    assert!(extracted.header.as_ref().unwrap().category.is_empty());
+}
+
+#[test]
+fn criteria_are_combined() {
+    let format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let everything = format.extract(&Filter::new());
+    assert_eq!(everything.tables.len(), format.tables.len());
+    assert_eq!(everything.constants.len(), format.constants.len());
+
+    let filter = Filter::new()
+        .category("Axes")
+        .title(Regex::new("AXIS$").unwrap())
+        .title(Regex::new("^SNM").unwrap());
+    let extracted = format.extract(&filter);
+    assert_eq!(
+        titles(&extracted.tables),
+        vec![
+            "LAMFA_ROWAXIS",
+            "SNM16GKUB",
+            "LAMFA_COLAXIS",
+            "SNM16ZUUB",
+            "TVUB_AXIS"
+        ]
+    );
+}
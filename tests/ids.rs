@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use xdftuneparser::{
+    data_types::*,
+    ids::{IdOverflow, ItemRef},
+};
+
+const AMB_XDF: &str = "tests/8E0909518AK_368072_NEF_STG_1v7.xdf";
+
+fn table(uid: Option<u32>, title: &str, link: Option<u32>) -> XDFTable {
+    let embedinfo = link.map(|uid| EmbedInfo {
+        etype: Some(3),
+        linkobjid: Some(uid),
+    });
+    XDFTable {
+        uid,
+        title: Some(title.into()),
+        axis: vec![XDFAxis {
+            id: Some("x".into()),
+            embedinfo,
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+fn linked_titles(format: &XDFFormat) -> Vec<(Option<String>, Option<String>)> {
+    format
+        .ids()
+        .links()
+        .iter()
+        .map(|link| {
+            let target = format.tables.iter().find(|t| t.uid == Some(link.target));
+            (
+                format.tables[link.table].title.clone(),
+                target.and_then(|t| t.title.clone()),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn bundled_ids_are_consistent() {
+    let format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let mut ids = format.ids();
+    assert_eq!(ids.duplicates().count(), 0);
+    assert_eq!(ids.dangling_links().count(), 0);
+    assert_eq!(ids.links().len(), 19);
+
+    let first = ids.allocate();
+    let second = ids.allocate();
+    assert_ne!(first, second);
+    assert!(format.tables.iter().all(|t| t.uid != Some(first)));
+    assert!(format.constants.iter().all(|c| c.uid != Some(first)));
+}
+
+#[test]
+fn renumbering_keeps_links() {
+    let mut format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let before = linked_titles(&format);
+    let mapping = format.renumber_ids(0x1000).unwrap();
+
+    assert_eq!(format.tables[0].uid, Some(0x1000));
+    assert_eq!(
+        format.constants[0].uid,
+        Some(0x1000 + format.tables.len() as u32)
+    );
+    assert_eq!(mapping.len(), format.tables.len() + format.constants.len());
+    assert_eq!(linked_titles(&format), before);
+    assert_eq!(format.ids().dangling_links().count(), 0);
+}
+
+#[test]
+fn renumbering_past_the_largest_id_fails() {
+    let mut format = XDFFormat::default();
+    format.tables.push(table(Some(0x10), "A", None));
+    format.tables.push(table(Some(0x11), "B", Some(0x10)));
+    let unchanged = format.clone();
+
+    assert_eq!(
+        format.renumber_ids(u32::MAX),
+        Err(IdOverflow {
+            first: u32::MAX,
+            items: 2
+        })
+    );
+    assert_eq!(format, unchanged);
+
+    let mapping = format.renumber_ids(u32::MAX - 1).unwrap();
+    assert_eq!(mapping[&0x11], u32::MAX);
+    assert_eq!(format.tables[1].uid, Some(u32::MAX));
+}
+
+#[test]
+fn renumbering_skips_dangling_link_targets() {
+    let mut format = XDFFormat::default();
+    format.tables.push(table(Some(0x100), "A", Some(0x2)));
+    format.tables.push(table(Some(0x200), "B", None));
+
+    let mapping = format.renumber_ids(1).unwrap();
+    assert_eq!(mapping, HashMap::from([(0x100, 1), (0x200, 3)]));
+    assert_eq!(format.tables[1].uid, Some(3));
+    // Still dangling rather than pointing at B
+    assert_eq!(
+        format.tables[0].axis[0]
+            .embedinfo
+            .as_ref()
+            .unwrap()
+            .linkobjid,
+        Some(0x2)
+    );
+    assert_eq!(format.ids().dangling_links().count(), 1);
+
+    let mut format = XDFFormat::default();
+    format.tables.push(table(Some(0x1), "A", Some(0x2)));
+    format.tables.push(table(Some(0x1), "B", None));
+    let changes = format.make_ids_unique();
+    assert_eq!(changes[0].new, 0x3);
+}
+
+#[test]
+fn duplicates_are_made_unique() {
+    let mut format = XDFFormat::default();
+    format.tables.push(table(Some(0x10), "Axis", None));
+    format.tables.push(table(Some(0x10), "Copy", None));
+    format.tables.push(table(None, "New", Some(0x10)));
+    format.constants.push(XDFConstant {
+        uid: Some(0x20),
+        ..Default::default()
+    });
+
+    let ids = format.ids();
+    let duplicates: Vec<_> = ids.duplicates().collect();
+    assert_eq!(
+        duplicates,
+        vec![(0x10, &[ItemRef::Table(0), ItemRef::Table(1)][..])]
+    );
+    assert_eq!(ids.items(0x20), &[ItemRef::Constant(0)]);
+
+    let changes = format.make_ids_unique();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(
+        (changes[0].item, changes[0].old),
+        (ItemRef::Table(1), Some(0x10))
+    );
+    assert_eq!((changes[1].item, changes[1].old), (ItemRef::Table(2), None));
+    assert_eq!(format.tables[1].uid, Some(0x21));
+    assert_eq!(format.tables[2].uid, Some(0x22));
+    assert_eq!(format.ids().duplicates().count(), 0);
+    assert_eq!(
+        linked_titles(&format),
+        vec![(Some("New".into()), Some("Axis".into()))]
+    );
+}
+
+#[test]
+fn remapped_ids_update_links() {
+    let mut format = XDFFormat::default();
+    format.tables.push(table(Some(0x10), "Axis", None));
+    format.tables.push(table(Some(0x11), "Map", Some(0x10)));
+    format
+        .tables
+        .push(table(Some(0x12), "Dangling", Some(0x99)));
+
+    format.remap_ids(&HashMap::from([(0x10, 0x500)]));
+    assert_eq!(format.tables[0].uid, Some(0x500));
+    assert_eq!(format.tables[1].uid, Some(0x11));
+    let links: Vec<_> = format.ids().links().iter().map(|l| l.target).collect();
+    assert_eq!(links, vec![0x500, 0x99]);
+    assert_eq!(format.ids().dangling_links().count(), 1);
+}
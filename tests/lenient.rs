@@ -0,0 +1,164 @@
+use std::fs::File;
+
+use xdftuneparser::{
+    data_types::*,
+    encoding::Encoding,
+    error::{ErrorKind, WarningKind},
+    parse_buffer, parse_buffer_with_options,
+    parser::ParseOptions,
+};
+
+const DOC: &str = r#"<XDFFORMAT version="1.70">
+  <XDFHEADER>
+    <deftitle>Newer TunerPro</deftitle>
+    <colorscheme name="dark"><accent>0xFF00FF</accent></colorscheme>
+  </XDFHEADER>
+  <XDFCONSTANT uniqueid="0x3BFE" vislevel="2">
+    <title>CDTES</title>
+    <mask>0x1</mask>
+  </XDFCONSTANT>
+</XDFFORMAT>"#;
+
+const LENIENT: ParseOptions = ParseOptions {
+    strict: false,
+    encoding: Encoding::Auto,
+};
+
+#[test]
+fn strict_rejects_unknown_elements() {
+    let err = parse_buffer(DOC.as_bytes()).unwrap().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnknownType("colorscheme".into()));
+}
+
+#[test]
+fn lenient_collects_warnings() {
+    let (element, warnings) = parse_buffer_with_options(DOC.as_bytes(), LENIENT).unwrap();
+    let XDFElement::XDFFormat(format) = element else {
+        panic!("expected XDFFORMAT");
+    };
+    assert_eq!(
+        format.header.unwrap().deftitle.as_deref(),
+        Some("Newer TunerPro")
+    );
+    assert_eq!(format.constants[0].title.as_deref(), Some("CDTES"));
+
+    let kinds: Vec<_> = warnings
+        .iter()
+        .map(|w| (&w.kind, w.path.as_str()))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (
+                &WarningKind::UnknownElement(RawElement {
+                    name: "colorscheme".into(),
+                    attributes: vec![("name".into(), "dark".into())],
+                    children: vec![RawNode::Element(RawElement {
+                        name: "accent".into(),
+                        attributes: vec![],
+                        children: vec![RawNode::Text("0xFF00FF".into())],
+                    })],
+                }),
+                "XDFFORMAT/XDFHEADER/colorscheme"
+            ),
+            (
+                &WarningKind::UnknownAttribute {
+                    name: "vislevel".into(),
+                    value: "2".into()
+                },
+                "XDFFORMAT/XDFCONSTANT[uid=0x3BFE]"
+            ),
+            (
+                &WarningKind::UnexpectedElement(XDFElement::Mask(1)),
+                "XDFFORMAT/XDFCONSTANT[uid=0x3BFE]"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn bundled_xdf_has_no_warnings() {
+    let file = File::open("tests/8E0909518AK_368072_NEF_STG_1v7.xdf").unwrap();
+    let (_, warnings) = parse_buffer_with_options(file, LENIENT).unwrap();
+    assert_eq!(warnings, vec![]);
+}
+
+#[test]
+fn unknown_content_is_kept_in_extras() {
+    let doc = r#"<XDFFORMAT version="1.70">
+  <XDFHEADER>
+    <deftitle>Newer TunerPro</deftitle>
+    <colorscheme name="dark" />
+    <notes>first</notes>
+  </XDFHEADER>
+  <XDFTABLE uniqueid="0x1" flags="0x0" vislevel="1">
+    <XDFAXIS id="z" color="red">
+      <EMBEDDEDDATA mmedaddress="0x100" mmedelementsizebits="8" mmedbitoffset="3" />
+    </XDFAXIS>
+  </XDFTABLE>
+  <XDFCONSTANT uniqueid="0x3BFE" vislevel="2">
+    <title>CDTES</title>
+    <notes>keep me</notes>
+  </XDFCONSTANT>
+</XDFFORMAT>"#;
+    let (element, _) = parse_buffer_with_options(doc.as_bytes(), LENIENT).unwrap();
+    let XDFElement::XDFFormat(format) = element else {
+        panic!("expected XDFFORMAT");
+    };
+
+    let raw = |name: &str, attributes: &[(&str, &str)], text: Option<&str>| RawElement {
+        name: name.into(),
+        attributes: attributes
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect(),
+        children: text.map(|t| RawNode::Text(t.into())).into_iter().collect(),
+    };
+    let attributes = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect()
+    };
+
+    assert_eq!(
+        format.header.unwrap().extras,
+        Extras {
+            attributes: vec![],
+            children: vec![
+                ExtraChild {
+                    position: 1,
+                    element: raw("colorscheme", &[("name", "dark")], None),
+                },
+                ExtraChild {
+                    position: 2,
+                    element: raw("notes", &[], Some("first")),
+                },
+            ],
+        }
+    );
+    let table = &format.tables[0];
+    assert_eq!(table.extras.attributes, attributes(&[("vislevel", "1")]));
+    assert_eq!(
+        table.axis[0].extras.attributes,
+        attributes(&[("color", "red")])
+    );
+    assert_eq!(
+        table.axis[0]
+            .embeddeddata
+            .as_ref()
+            .unwrap()
+            .extras
+            .attributes,
+        attributes(&[("mmedbitoffset", "3")])
+    );
+    let constant = &format.constants[0];
+    assert_eq!(constant.extras.attributes, attributes(&[("vislevel", "2")]));
+    assert_eq!(
+        constant.extras.children,
+        vec![ExtraChild {
+            position: 1,
+            element: raw("notes", &[], Some("keep me")),
+        }]
+    );
+}
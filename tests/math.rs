@@ -0,0 +1,80 @@
+use xdftuneparser::{
+    data_types::*,
+    math::{Equation, MathError},
+};
+
+const AMB_XDF: &str = "tests/8E0909518AK_368072_NEF_STG_1v7.xdf";
+
+fn eval(expression: &str, x: f64) -> f64 {
+    Equation::parse(expression, &[]).unwrap().eval(x)
+}
+
+#[test]
+fn evaluates_sample_equations() {
+    assert_eq!(eval("X", 7.0), 7.0);
+    assert_eq!(eval("X * .250000", 10.0), 2.5);
+    assert_eq!(eval("X/4", 10.0), 2.5);
+    assert_eq!(eval("x*0.001526", 1000.0), 1.526);
+    assert_eq!(eval("(X*0.75)-48", 100.0), 27.0);
+    assert_eq!(eval("0.750000 * X+ -48.000000", 100.0), 27.0);
+    assert_eq!(eval("X*0.019531-50", 0.0), -50.0);
+    assert!((eval("(((10*(0.023438 * X))+300)*0.0145037738)-14.5", 100.0) + 9.8089).abs() < 1e-4);
+    assert_eq!(eval("-2^2", 0.0), -4.0);
+    assert_eq!(eval("2^3^2", 0.0), 512.0);
+    assert_eq!(eval("1 - 2 - 3", 0.0), -4.0);
+}
+
+#[test]
+fn inverts_equations_using_x_once() {
+    for (expression, x) in [
+        ("X", 12.0),
+        ("(X*0.75)-48", 100.0),
+        ("X*0.019531-50", 200.0),
+        ("(((10*(0.023438 * X))+300)*0.0145037738)-14.5", 140.0),
+        ("100 / X", 8.0),
+        ("10 - X", 3.0),
+        ("-X^2", 3.0),
+        ("2^X", 5.0),
+    ] {
+        let equation = Equation::parse(expression, &[]).unwrap();
+        let inverted = equation.invert(equation.eval(x)).unwrap();
+        assert!((inverted - x).abs() < 1e-9, "{expression}: {inverted}");
+    }
+    assert_eq!(
+        Equation::parse("X*X", &[]).unwrap().invert(4.0),
+        Err(MathError::NotInvertible)
+    );
+    assert_eq!(
+        Equation::parse("X*0", &[]).unwrap().invert(4.0),
+        Err(MathError::NotInvertible)
+    );
+}
+
+#[test]
+fn rejects_unsupported_equations() {
+    assert_eq!(Equation::parse("X*", &[]), Err(MathError::Syntax(2)));
+    assert_eq!(Equation::parse("(X", &[]), Err(MathError::Syntax(2)));
+    assert_eq!(Equation::parse("X Y", &[]), Err(MathError::Syntax(2)));
+    assert_eq!(
+        Equation::parse("X*A", &[]),
+        Err(MathError::UnknownName("A".to_string()))
+    );
+    let vars = ["A".to_string()];
+    assert_eq!(Equation::parse("X*A", &vars).unwrap().eval(3.0), 9.0);
+}
+
+#[test]
+fn every_sample_equation_round_trips() {
+    let format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let axes = format.tables.iter().flat_map(|t| &t.axis);
+    let maths: Vec<&Math> = axes
+        .filter_map(|a| a.math.as_ref())
+        .chain(format.constants.iter().filter_map(|c| c.math.as_ref()))
+        .collect();
+    assert!(maths.len() > 100);
+    for math in maths {
+        let equation = math.equation().unwrap();
+        let inverted = equation.invert(equation.eval(100.0)).unwrap();
+        assert!((inverted - 100.0).abs() < 1e-6, "{math:?}");
+    }
+}
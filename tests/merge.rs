@@ -0,0 +1,184 @@
+use xdftuneparser::{
+    builder::{AxisBuilder, ConstantBuilder, TableBuilder},
+    data_types::*,
+    ids::ItemRef,
+    merge::{Conflict, ConflictKind, MergePolicy, Resolution},
+};
+
+const AMB_XDF: &str = "tests/8E0909518AK_368072_NEF_STG_1v7.xdf";
+
+fn category(index: u32, name: &str) -> Category {
+    Category {
+        index: Some(index),
+        name: Some(name.into()),
+    }
+}
+
+fn title<'a>(tables: &'a [XDFTable], title: &str) -> &'a XDFTable {
+    tables
+        .iter()
+        .find(|t| t.title.as_deref() == Some(title))
+        .unwrap()
+}
+
+/// In-house additions to the bundled XDF, with its own category numbering.
+fn additions(ours: &XDFFormat) -> XDFFormat {
+    let mut axis = title(&ours.tables, "TVUB_AXIS").clone();
+    axis.uid = Some(0x50);
+    axis.catmem[0].category = Some(3);
+
+    let boost = TableBuilder::new("Boost limit")
+        .uid(0x14DAE)
+        .category(0xFF)
+        .address(0x70000)
+        .cols(5)
+        .x_axis(AxisBuilder::linked(0x50))
+        .y_axis(AxisBuilder::linked(0x51))
+        .build()
+        .unwrap();
+    let boost_axis = TableBuilder::new("Boost axis")
+        .uid(0x51)
+        .category(3)
+        .address(0x70010)
+        .rows(4)
+        .build()
+        .unwrap();
+    // Same title as an existing constant, different address
+    let cdtes = ConstantBuilder::new("CDTES")
+        .uid(0x3BFE)
+        .address(0x181B3)
+        .build()
+        .unwrap();
+    // Address of TVUB_AXIS data, but not matched since its title differs and it is a constant
+    let overlap = ConstantBuilder::new("Overlap")
+        .uid(0x52)
+        .address(0x14DAA)
+        .build()
+        .unwrap();
+
+    XDFFormat {
+        header: Some(XDFHeader {
+            category: vec![category(3, "Axes"), category(0xFF, "Boost")],
+            ..Default::default()
+        }),
+        tables: vec![axis, boost, boost_axis],
+        constants: vec![cdtes, overlap],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn merging_with_itself_changes_nothing() {
+    let mut format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let report = format.merge(&format.clone(), MergePolicy::default());
+    assert_eq!(report.conflicts, vec![]);
+    assert_eq!(report.added, vec![]);
+    assert!(report.ids.iter().all(|(from, to)| from == to));
+    assert_eq!(format, XDFFormat::from_path(AMB_XDF).unwrap());
+}
+
+#[test]
+fn additions_are_merged() {
+    let mut format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let ours = format.clone();
+    let theirs = additions(&format);
+    let report = format.merge(&theirs, MergePolicy::default());
+
+    let tvub_axis = ItemRef::Table(
+        ours.tables
+            .iter()
+            .position(|t| t.uid == Some(0x14DA9))
+            .unwrap(),
+    );
+    let cdtes = ItemRef::Constant(
+        ours.constants
+            .iter()
+            .position(|c| c.uid == Some(0x3BFE))
+            .unwrap(),
+    );
+    assert_eq!(
+        report.conflicts,
+        vec![
+            Conflict {
+                kind: ConflictKind::Address,
+                ours: cdtes,
+                theirs: ItemRef::Constant(0),
+                resolution: Resolution::KeepOurs,
+            },
+            Conflict {
+                kind: ConflictKind::Overlap,
+                ours: tvub_axis,
+                theirs: ItemRef::Constant(1),
+                resolution: Resolution::KeepOurs,
+            },
+        ]
+    );
+    assert_eq!(report.added, vec![ItemRef::Table(1), ItemRef::Table(2)]);
+    assert_eq!(format.tables.len(), ours.tables.len() + 2);
+    assert_eq!(format.constants, ours.constants);
+
+    // Categories are matched by name, a clashing index is renumbered
+    let categories = &format.header.as_ref().unwrap().category;
+    assert_eq!(
+        categories,
+        &vec![category(0xFF, "Axes"), category(0, "Boost")]
+    );
+    let boost = title(&format.tables, "Boost limit");
+    let boost_axis = title(&format.tables, "Boost axis");
+    assert_eq!(boost.catmem[0].category, Some(0));
+    assert_eq!(boost_axis.catmem[0].category, Some(0xFF));
+
+    // Links follow the matched axis table and the renumbered new one
+    assert_ne!(boost.uid, Some(0x14DAE));
+    assert_eq!(boost_axis.uid, Some(0x51));
+    assert_eq!(report.ids[&0x50], 0x14DA9);
+    let links: Vec<_> = boost
+        .axis
+        .iter()
+        .filter_map(|a| a.embedinfo.as_ref()?.linkobjid)
+        .collect();
+    assert_eq!(links, vec![0x14DA9, 0x51]);
+    let ids = format.ids();
+    assert_eq!(ids.duplicates().count(), 0);
+    assert_eq!(ids.dangling_links().count(), 0);
+}
+
+#[test]
+fn conflicts_follow_the_policy() {
+    let ours = XDFFormat::from_path(AMB_XDF).unwrap();
+    let theirs = additions(&ours);
+
+    let mut format = ours.clone();
+    let report = format.merge(&theirs, MergePolicy::all(Resolution::TakeTheirs));
+    assert_eq!(report.conflicts.len(), 2);
+    let cdtes = format
+        .constants
+        .iter()
+        .find(|c| c.uid == Some(0x3BFE))
+        .unwrap();
+    assert_eq!(
+        cdtes.embedded_data.as_ref().unwrap().mmedaddress,
+        Some(0x181B3)
+    );
+    assert_eq!(format.constants.len(), ours.constants.len() + 1);
+    assert!(format.tables.iter().all(|t| t.uid != Some(0x14DA9)));
+    assert_eq!(format.ids().dangling_links().count(), 2);
+
+    let mut format = ours.clone();
+    let policy = MergePolicy {
+        address: Resolution::KeepBoth,
+        ..Default::default()
+    };
+    format.merge(&theirs, policy);
+    let titles = format
+        .constants
+        .iter()
+        .filter(|c| c.title.as_deref() == Some("CDTES"))
+        .count();
+    assert_eq!(titles, 2);
+    assert!(format
+        .constants
+        .iter()
+        .all(|c| c.title.as_deref() != Some("Overlap")));
+    assert_eq!(format.ids().duplicates().count(), 0);
+}
@@ -0,0 +1,67 @@
+use std::fs::File;
+
+use xdftuneparser::{
+    data_types::XDFElement,
+    error::ErrorKind,
+    parse_buffer,
+    reader::{XdfItem, XdfReader},
+};
+
+const XDF: &str = "tests/8E0909518AK_368072_NEF_STG_1v7.xdf";
+
+#[test]
+fn items_match_full_parse() {
+    let XDFElement::XDFFormat(format) = parse_buffer(File::open(XDF).unwrap()).unwrap().unwrap()
+    else {
+        panic!("expected XDFFORMAT");
+    };
+
+    let mut reader = XdfReader::new(File::open(XDF).unwrap());
+    let items: Vec<_> = reader.items().collect::<Result<_, _>>().unwrap();
+    assert_eq!(reader.version(), Some("1.50"));
+
+    assert_eq!(
+        items.first(),
+        Some(&XdfItem::Header(format.header.unwrap()))
+    );
+    let tables: Vec<_> = items
+        .iter()
+        .filter_map(|i| match i {
+            XdfItem::Table(t) => Some(t.clone()),
+            _ => None,
+        })
+        .collect();
+    let constants: Vec<_> = items
+        .iter()
+        .filter_map(|i| match i {
+            XdfItem::Constant(c) => Some(c.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(tables, format.tables);
+    assert_eq!(constants, format.constants);
+    assert_eq!(items.len(), 1 + tables.len() + constants.len());
+}
+
+#[test]
+fn stop_early() {
+    let mut reader = XdfReader::new(File::open(XDF).unwrap());
+    let tvub = reader
+        .items()
+        .find_map(|i| match i.unwrap() {
+            XdfItem::Table(t) if t.title.as_deref() == Some("TVUB") => Some(t),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(tvub.uid, Some(0x14DAE));
+}
+
+#[test]
+fn wrong_root() {
+    let mut reader = XdfReader::new("<XDFTABLE />".as_bytes());
+    let mut items = reader.items();
+    let err = items.next().unwrap().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnexpectedRoot("XDFTABLE".into()));
+    assert_eq!(err.path.as_deref(), Some("XDFTABLE"));
+    assert!(items.next().is_none());
+}
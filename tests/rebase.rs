@@ -0,0 +1,164 @@
+use xdftuneparser::{
+    builder::{AxisBuilder, ConstantBuilder, TableBuilder},
+    data_types::*,
+    ids::ItemRef,
+    rebase::Method,
+};
+
+/// Bin with distinctive contents.
+fn source() -> Vec<u8> {
+    let mut state = 0x2545_F491_u32;
+    (0..0x1000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+/// Next software revision, 0x40 bytes of code were added at 0x80 and one map value was changed.
+fn target(source: &[u8]) -> Vec<u8> {
+    let mut target = source[..0x80].to_vec();
+    target.extend(std::iter::repeat_n(0x00, 0x40));
+    target.extend(&source[0x80..]);
+    target[0x150] ^= 0xFF;
+    // Copy of the twin table somewhere else
+    target.copy_within(0x540..0x548, 0x900);
+    target
+}
+
+fn definition() -> XDFFormat {
+    let map = TableBuilder::new("Map")
+        .address(0x100)
+        .rows(4)
+        .cols(4)
+        .element_bits(16)
+        .x_axis(AxisBuilder::embedded(0xF0, 16))
+        .build()
+        .unwrap();
+    let twin = TableBuilder::new("Twin")
+        .address(0x500)
+        .cols(8)
+        .build()
+        .unwrap();
+    let constant = ConstantBuilder::new("Const")
+        .address(0x200)
+        .build()
+        .unwrap();
+    let flag = XDFFlag {
+        title: Some("Bit".into()),
+        embedded_data: Some(EmbeddedData {
+            mmedaddress: Some(0x200),
+            mmedelementsizebits: Some(8),
+            ..Default::default()
+        }),
+        mask: Some(0x1),
+        ..Default::default()
+    };
+    XDFFormat {
+        tables: vec![map, twin],
+        constants: vec![constant],
+        flags: vec![flag],
+        ..Default::default()
+    }
+}
+
+fn address(data: &Option<EmbeddedData>) -> Option<u32> {
+    data.as_ref()?.mmedaddress
+}
+
+#[test]
+fn items_are_found_in_the_new_revision() {
+    let source = source();
+    let (rebased, report) = definition().rebase(&source, &target(&source));
+
+    let summary: Vec<_> = report
+        .iter()
+        .map(|r| (r.item, r.from, r.to, r.method))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (ItemRef::Table(0), 0x100, Some(0x140), Method::Signature),
+            (ItemRef::Table(1), 0x500, Some(0x540), Method::Signature),
+            (ItemRef::Constant(0), 0x200, Some(0x240), Method::Neighbour),
+            (ItemRef::Flag(0), 0x200, Some(0x240), Method::Neighbour),
+        ]
+    );
+    // One of 40 bytes changed
+    assert_eq!(report[0].confidence, 39.0 / 40.0);
+    // Found twice
+    assert_eq!(report[1].confidence, 0.5);
+    assert_eq!(report[2].confidence, 0.5);
+
+    let map = &rebased.tables[0];
+    assert_eq!(
+        address(&map.axis_by_id("x").unwrap().embeddeddata),
+        Some(0xF0 + 0x40)
+    );
+    assert_eq!(
+        address(&map.axis_by_id("z").unwrap().embeddeddata),
+        Some(0x140)
+    );
+    assert_eq!(address(&rebased.constants[0].embedded_data), Some(0x240));
+    assert_eq!(address(&rebased.flags[0].embedded_data), Some(0x240));
+}
+
+#[test]
+fn items_without_signature_stay_put() {
+    let source = source();
+    let mut format = definition();
+    format.tables.clear();
+    let (rebased, report) = format.rebase(&source, &target(&source));
+
+    assert!(report
+        .iter()
+        .all(|r| r.method == Method::NotFound && r.to.is_none()));
+    assert_eq!(rebased, format);
+}
+
+#[test]
+fn axes_are_located_on_their_own() {
+    let source = source();
+    let mut target = target(&source);
+    target[0x150] ^= 0xFF;
+    // The x axis of the map moved apart from its data
+    target.copy_within(0x130..0x138, 0xA00);
+    target[0x130..0x138].fill(0x00);
+    let (rebased, report) = definition().rebase(&source, &target);
+
+    let map = &report[0];
+    assert_eq!(map.to, Some(0x140));
+    let blocks: Vec<_> = map
+        .blocks
+        .iter()
+        .map(|b| (b.from, b.to, b.method))
+        .collect();
+    assert_eq!(
+        blocks,
+        vec![
+            (0xF0, Some(0xA00), Method::Signature),
+            (0x100, Some(0x140), Method::Signature),
+        ]
+    );
+    assert_eq!(map.blocks[0].confidence, 1.0);
+    let x = rebased.tables[0].axis_by_id("x").unwrap();
+    assert_eq!(address(&x.embeddeddata), Some(0xA00));
+
+    // Not found anywhere, the axis takes the shift of its table
+    target[0xA00..0xA08].fill(0x00);
+    let (rebased, report) = definition().rebase(&source, &target);
+    let axis = report[0].blocks[0];
+    assert_eq!((axis.to, axis.method), (Some(0x130), Method::Item));
+    assert_eq!(axis.confidence, 0.0);
+    let x = rebased.tables[0].axis_by_id("x").unwrap();
+    assert_eq!(address(&x.embeddeddata), Some(0x130));
+
+    // Flags are not searched for, their data moves with them
+    let flag = &report[3];
+    assert_eq!(flag.method, Method::Neighbour);
+    assert_eq!(flag.blocks[0].method, Method::Item);
+    assert_eq!(flag.blocks[0].to, flag.to);
+}
@@ -0,0 +1,265 @@
+use xdftuneparser::{
+    data_types::*, encoding::Encoding, parser::ParseOptions, writer::WriteOptions,
+};
+
+const AMB_XDF: &str = "tests/8E0909518AK_368072_NEF_STG_1v7.xdf";
+
+const CANONICAL: WriteOptions = WriteOptions { canonical: true };
+
+/// Every element the parser knows, with every attribute and child it reads.
+const EVERY_ELEMENT: &str = r#"<XDFFORMAT version="1.60">
+  <XDFHEADER>
+    <flags>0x1</flags>
+    <fileversion>2.1</fileversion>
+    <deftitle>Synthetic &amp; complete</deftitle>
+    <description>Line one&#013;&#010;Line two, 25&#176;C</description>
+    <author>Someone</author>
+    <baseoffset>0</baseoffset>
+    <DEFAULTS datasizeinbits="16" sigdigits="2" outputtype="1" signed="1" lsbfirst="1" float="0" />
+    <REGION type="0xFFFFFFFF" startaddress="0x0" size="0x100000" regionflags="0x0" />
+    <CATEGORY index="0x0" name="Fuel" />
+    <CATEGORY index="0x1" name="Ignition" />
+  </XDFHEADER>
+  <XDFTABLE uniqueid="0x6E81" flags="0x30">
+    <title>KFMIRL</title>
+    <description>Engine load desired</description>
+    <CATEGORYMEM index="0" category="1" />
+    <CATEGORYMEM index="1" category="2" />
+    <XDFAXIS id="x" uniqueid="0x0">
+      <EMBEDDEDDATA mmedtypeflags="0x02" mmedaddress="0x1EF7E" mmedelementsizebits="16" mmedcolcount="16" mmedmajorstridebits="0" mmedminorstridebits="0" />
+      <units>RPM</units>
+      <indexcount>16</indexcount>
+      <decimalpl>0</decimalpl>
+      <min>-40.500000</min>
+      <max>0.0000125</max>
+      <outputtype>1</outputtype>
+      <embedinfo type="3" linkobjid="0x1045" />
+      <datatype>0</datatype>
+      <unittype>0</unittype>
+      <DALINK index="0" />
+      <LABEL index="0" value="1000" />
+      <LABEL index="1" value="&lt;2000&gt;" />
+      <MATH equation="0.250000 * X">
+        <VAR id="X" />
+      </MATH>
+    </XDFAXIS>
+    <XDFAXIS id="z">
+      <EMBEDDEDDATA mmedtypeflags="0x06" mmedaddress="0x1EFBE" mmedelementsizebits="16" mmedrowcount="16" mmedcolcount="16" mmedmajorstridebits="-32" mmedminorstridebits="0" />
+      <MATH />
+    </XDFAXIS>
+  </XDFTABLE>
+  <XDFCONSTANT uniqueid="0x3BFE">
+    <title>CDTES</title>
+    <description></description>
+    <CATEGORYMEM index="0" category="2" />
+    <EMBEDDEDDATA mmedaddress="0x181B2" mmedelementsizebits="8" mmedmajorstridebits="0" mmedminorstridebits="0" />
+    <units>-</units>
+    <decimalpl>2</decimalpl>
+    <outputtype>2</outputtype>
+    <datatype>0</datatype>
+    <unittype>0</unittype>
+    <DALINK index="0" />
+    <MATH equation="X">
+      <VAR id="X" />
+    </MATH>
+  </XDFCONSTANT>
+  <XDFPATCH uniqueid="0x2A1">
+    <title>Disable rear O2 sensors</title>
+    <description>Patches out the post-cat lambda check</description>
+    <CATEGORYMEM index="0" category="1" />
+    <XDFPATCHENTRY name="Jump" address="0x1A2B4" datasize="0x2" patchdata="0DFB" basedata="3DF7" />
+  </XDFPATCH>
+  <XDFFLAG uniqueid="0x1A3C">
+    <title>Tank venting diagnosis</title>
+    <description>Codeword bit</description>
+    <CATEGORYMEM index="0" category="2" />
+    <EMBEDDEDDATA mmedaddress="0x181B2" mmedelementsizebits="8" mmedmajorstridebits="0" mmedminorstridebits="0" />
+    <mask>0x4</mask>
+  </XDFFLAG>
+  <XDFCHECKSUM uniqueid="0x5A0">
+    <title>Main checksum</title>
+    <description>Sum of words</description>
+    <CATEGORYMEM index="0" category="1" />
+    <REGION>
+      <datastart>0x10000</datastart>
+      <dataend>0x1FFFB</dataend>
+      <datasizebits>0x10</datasizebits>
+      <storeaddress>0x1FFFC</storeaddress>
+      <calculationmethod>0x1</calculationmethod>
+    </REGION>
+    <REGION />
+  </XDFCHECKSUM>
+</XDFFORMAT>"#;
+
+/// Unknown content, only readable in lenient mode, kept in extras.
+const UNKNOWN_CONTENT: &str = r#"<XDFFORMAT version="1.70">
+  <XDFHEADER colorscheme="dark">
+    <deftitle>Newer TunerPro</deftitle>
+    <plugin name="wideband"><port>COM3</port>text<baud>9600</baud></plugin>
+  </XDFHEADER>
+  <XDFTABLE uniqueid="0x1" flags="0x0" vislevel="2">
+    <XDFAXIS id="z" scaling="log">
+      <EMBEDDEDDATA mmedaddress="0x100" mmedelementsizebits="8" mmedfuture="1">
+        <hint>fast</hint>
+      </EMBEDDEDDATA>
+      <colors />
+    </XDFAXIS>
+  </XDFTABLE>
+  <XDFCONSTANT uniqueid="0x2" vislevel="3">
+    <title>Lenient</title>
+    <note author="a">keep me</note>
+  </XDFCONSTANT>
+</XDFFORMAT>"#;
+
+fn write(format: &XDFFormat, options: WriteOptions) -> Vec<u8> {
+    let mut out = Vec::new();
+    format.write_to_with_options(&mut out, options).unwrap();
+    out
+}
+
+/// Parses, writes and parses again, checking the model survives and writing it again gives the same bytes.
+fn assert_round_trip(format: &XDFFormat, options: ParseOptions) {
+    let written = write(format, CANONICAL);
+    let (reparsed, _) = XDFFormat::parse_with_options(written.as_slice(), options).unwrap();
+    assert_eq!(&reparsed, format);
+    assert_eq!(write(&reparsed, CANONICAL), written);
+}
+
+#[test]
+fn bundled_xdf_round_trips() {
+    let format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let mut written = Vec::new();
+    format.write_to(&mut written).unwrap();
+    assert_eq!(XDFFormat::parse(written.as_slice()).unwrap(), format);
+}
+
+#[test]
+fn every_element_round_trips() {
+    let format: XDFFormat = EVERY_ELEMENT.parse().unwrap();
+    assert_eq!(format.header.as_ref().unwrap().category.len(), 2);
+    assert_eq!(format.tables[0].axis.len(), 2);
+    assert_eq!(format.checksums[0].regions.len(), 2);
+    assert_round_trip(&format, ParseOptions::default());
+}
+
+#[test]
+fn unknown_content_round_trips() {
+    let lenient = ParseOptions {
+        strict: false,
+        encoding: Encoding::Auto,
+    };
+    let (format, warnings) =
+        XDFFormat::parse_with_options(UNKNOWN_CONTENT.as_bytes(), lenient).unwrap();
+    assert!(!warnings.is_empty());
+    assert_round_trip(&format, lenient);
+}
+
+#[test]
+fn unknown_children_keep_their_place() {
+    let doc = r#"<XDFFORMAT version="1.70">
+  <XDFCONSTANT uniqueid="0x2">
+    <first />
+    <title>Lenient</title>
+    <CATEGORYMEM index="0" category="1" />
+    <between />
+    <CATEGORYMEM index="1" category="2" />
+    <EMBEDDEDDATA mmedaddress="0x100" mmedelementsizebits="8" />
+    <after>text</after>
+    <units>kPa</units>
+  </XDFCONSTANT>
+</XDFFORMAT>"#;
+    let lenient = ParseOptions {
+        strict: false,
+        encoding: Encoding::Auto,
+    };
+    let (format, _) = XDFFormat::parse_with_options(doc.as_bytes(), lenient).unwrap();
+    let written = String::from_utf8(write(&format, CANONICAL)).unwrap();
+    let lines: Vec<&str> = written.lines().map(str::trim).collect();
+    assert_eq!(
+        lines[2..lines.len() - 2],
+        [
+            "<first />",
+            "<title>Lenient</title>",
+            "<CATEGORYMEM index=\"0\" category=\"1\" />",
+            "<between />",
+            "<CATEGORYMEM index=\"1\" category=\"2\" />",
+            "<EMBEDDEDDATA mmedaddress=\"0x100\" mmedelementsizebits=\"8\" />",
+            "<after>text</after>",
+            "<units>kPa</units>",
+        ]
+    );
+}
+
+#[test]
+fn built_model_round_trips() {
+    let format = XDFFormat {
+        version: Some("1.50".into()),
+        constants: vec![XDFConstant {
+            uid: Some(0x10),
+            title: Some("Built \"in code\"".into()),
+            embedded_data: Some(EmbeddedData {
+                mmedaddress: Some(0x8000),
+                mmedelementsizebits: Some(16),
+                ..Default::default()
+            }),
+            math: Some(Math {
+                vars: vec!["X".into()],
+                expression: Some("X/4 - 10".into()),
+            }),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    assert_round_trip(&format, ParseOptions::default());
+}
+
+#[test]
+fn canonical_output_ignores_order_and_formatting() {
+    let a = r#"<!-- Written 02/02/2015 10:48:01 -->
+<XDFFORMAT version="1.50">
+  <XDFHEADER>
+    <CATEGORY index="0x1" name="Ignition" />
+    <CATEGORY index="0x0" name="Fuel" />
+  </XDFHEADER>
+  <XDFCONSTANT uniqueid="0x20"><title>B</title></XDFCONSTANT>
+  <XDFCONSTANT uniqueid="0x10">
+    <title>A</title>
+    <CATEGORYMEM index="1" category="2" />
+    <CATEGORYMEM index="0" category="1" />
+    <EMBEDDEDDATA mmedaddress="0x181B2" mmedelementsizebits="8" />
+  </XDFCONSTANT>
+</XDFFORMAT>"#;
+    let b = r#"<XDFFORMAT version="1.50">
+  <XDFCONSTANT uniqueid="16">
+    <title>A</title>
+    <!-- comments are not content -->
+    <CATEGORYMEM category="1" index="0" />
+    <CATEGORYMEM category="2" index="1" />
+    <EMBEDDEDDATA mmedelementsizebits="0x8" mmedaddress="98738" />
+  </XDFCONSTANT>
+  <XDFHEADER>
+    <CATEGORY name="Fuel" index="0" />
+    <CATEGORY name="Ignition" index="1" />
+  </XDFHEADER>
+  <XDFCONSTANT uniqueid="0X20">
+    <title>B</title>
+  </XDFCONSTANT>
+</XDFFORMAT>"#;
+    let a: XDFFormat = a.parse().unwrap();
+    let b: XDFFormat = b.parse().unwrap();
+    assert_ne!(a, b);
+
+    let written = write(&a, CANONICAL);
+    assert_eq!(written, write(&b, CANONICAL));
+    assert!(written.starts_with(b"<XDFFORMAT version=\"1.50\">\r\n  <XDFHEADER>\r\n"));
+}
+
+#[test]
+fn canonical_output_is_stable() {
+    let format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let written = write(&format, CANONICAL);
+    assert!(!written.starts_with(b"<!--"));
+
+    let reparsed = XDFFormat::parse(written.as_slice()).unwrap();
+    assert_eq!(write(&reparsed, CANONICAL), written);
+}
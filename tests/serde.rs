@@ -0,0 +1,84 @@
+#![cfg(feature = "serde")]
+
+use serde_json::json;
+use xdftuneparser::{data_types::*, encoding::Encoding, parser::ParseOptions, reader::XdfItem};
+
+const AMB_XDF: &str = "tests/8E0909518AK_368072_NEF_STG_1v7.xdf";
+
+#[test]
+fn bundled_xdf_round_trips_through_json() {
+    let format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let json = serde_json::to_string(&format).unwrap();
+    let reread: XDFFormat = serde_json::from_str(&json).unwrap();
+    assert_eq!(reread, format);
+}
+
+#[test]
+fn field_names_and_tags() {
+    let format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let tvub = format
+        .tables
+        .iter()
+        .find(|t| t.uid == Some(0x14DAE))
+        .unwrap();
+    let value = serde_json::to_value(XdfItem::Table(tvub.clone())).unwrap();
+
+    assert_eq!(value["type"], "table");
+    assert_eq!(value["title"], "TVUB");
+    assert!(value.get("extras").is_none());
+    let y = &value["axis"][1];
+    assert_eq!(y["embedinfo"], json!({ "type": 3, "linkobjid": 0x14DA9 }));
+    assert_eq!(y["embedded_data"]["mmedelementsizebits"], 16);
+
+    let header = serde_json::to_value(format.header.as_ref().unwrap()).unwrap();
+    assert_eq!(header["region"]["type"], 0);
+}
+
+#[test]
+fn missing_fields_take_defaults() {
+    let constant: XDFConstant = serde_json::from_value(json!({
+        "title": "CDTES",
+        "embedded_data": { "mmedaddress": 0x181B2 }
+    }))
+    .unwrap();
+    assert_eq!(
+        constant,
+        XDFConstant {
+            title: Some("CDTES".into()),
+            embedded_data: Some(EmbeddedData {
+                mmedaddress: Some(0x181B2),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn unknown_content_round_trips() {
+    let doc = r#"<XDFFORMAT version="1.70">
+  <XDFCONSTANT uniqueid="0x1" colour="red">
+    <title>Tagged</title>
+    <note lang="en">kept<b>bold</b></note>
+  </XDFCONSTANT>
+  <colorscheme name="dark" />
+</XDFFORMAT>"#;
+    let options = ParseOptions {
+        strict: false,
+        encoding: Encoding::Auto,
+    };
+    let (format, _) = XDFFormat::parse_with_options(doc.as_bytes(), options).unwrap();
+    let value = serde_json::to_value(&format).unwrap();
+    assert_eq!(
+        value["constants"][0]["extras"]["children"][0]["element"]["children"],
+        json!([{ "text": "kept" }, { "element": { "name": "b", "attributes": [], "children": [{ "text": "bold" }] } }])
+    );
+
+    let element = XDFElement::Title("Tagged".into());
+    assert_eq!(
+        serde_json::to_value(&element).unwrap(),
+        json!({ "element": "Title", "value": "Tagged" })
+    );
+    let reread: XDFFormat = serde_json::from_value(value).unwrap();
+    assert_eq!(reread, format);
+}
@@ -0,0 +1,213 @@
+use xdftuneparser::{bin::BinImage, data_types::*, table::ResolveError};
+
+const AMB_XDF: &str = "tests/8E0909518AK_368072_NEF_STG_1v7.xdf";
+
+fn math(expression: &str) -> Option<Math> {
+    Some(Math {
+        vars: vec!["X".to_string()],
+        expression: Some(expression.to_string()),
+    })
+}
+
+fn axis(id: &str, data: Option<EmbeddedData>, expression: &str) -> XDFAxis {
+    XDFAxis {
+        id: Some(id.to_string()),
+        embeddeddata: data,
+        math: math(expression),
+        ..Default::default()
+    }
+}
+
+fn data(address: u32, rows: Option<u32>, cols: Option<u32>) -> Option<EmbeddedData> {
+    Some(EmbeddedData {
+        mmedaddress: Some(address),
+        mmedelementsizebits: Some(8),
+        mmedrowcount: rows,
+        mmedcolcount: cols,
+        ..Default::default()
+    })
+}
+
+#[test]
+fn embedded_and_label_axes() {
+    let mut y = axis("y", None, "X*1000");
+    y.count = Some(2);
+    y.labels = vec![
+        Label {
+            index: Some(1),
+            value: Some("2000".to_string()),
+        },
+        Label {
+            index: Some(0),
+            value: Some(" 1000 ".to_string()),
+        },
+    ];
+    let table = XDFTable {
+        axis: vec![
+            axis("x", data(0x0, None, Some(3)), "X*10"),
+            y,
+            axis("z", data(0x3, Some(2), Some(3)), "X/2"),
+        ],
+        ..Default::default()
+    };
+    let bin = BinImage::new(vec![1, 2, 3, 10, 20, 30, 40, 50, 60]);
+
+    let resolved = table.resolve(&XDFFormat::default(), &bin).unwrap();
+    assert_eq!(resolved.x, vec![10.0, 20.0, 30.0]);
+    // Labels are display values, the axis math is not applied to them
+    assert_eq!(resolved.y, vec![1000.0, 2000.0]);
+    assert_eq!(
+        resolved.values,
+        vec![vec![5.0, 10.0, 15.0], vec![20.0, 25.0, 30.0]]
+    );
+    assert_eq!((resolved.rows(), resolved.cols()), (2, 3));
+    assert_eq!(resolved.get(1, 2), Some(30.0));
+    assert_eq!(resolved.get(2, 0), None);
+}
+
+#[test]
+fn counts_come_from_axes_when_data_has_none() {
+    let mut x = axis("x", None, "X");
+    x.count = Some(4);
+    let table = XDFTable {
+        axis: vec![x, axis("z", data(0x0, None, None), "X")],
+        ..Default::default()
+    };
+    let bin = BinImage::new(vec![1, 2, 3, 4]);
+
+    let resolved = table.resolve(&XDFFormat::default(), &bin).unwrap();
+    assert_eq!(resolved.values, vec![vec![1.0, 2.0, 3.0, 4.0]]);
+    assert_eq!(resolved.x, vec![0.0, 1.0, 2.0, 3.0]);
+    assert_eq!(resolved.y, vec![0.0]);
+
+    // Too few labels for the columns of the data
+    let mut short = table.clone();
+    short.axis[0].labels = vec![Label {
+        index: Some(0),
+        value: Some("1".to_string()),
+    }];
+    assert_eq!(
+        short.resolve(&XDFFormat::default(), &bin),
+        Err(ResolveError::AxisLength {
+            axis: "x".to_string(),
+            expected: 4,
+            found: 1
+        })
+    );
+    assert_eq!(
+        XDFTable::default().resolve(&XDFFormat::default(), &bin),
+        Err(ResolveError::MissingData)
+    );
+}
+
+#[test]
+fn linked_axis_uses_values_of_linked_table() {
+    let format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let tvub = format
+        .tables
+        .iter()
+        .find(|t| t.title.as_deref() == Some("TVUB"))
+        .unwrap();
+    let mut bin = vec![0; 0x20000];
+    // TVUB_AXIS, 5 bytes at 0x14DA9 scaled by 0.0704, followed by TVUB as 16 bit LSB first scaled by 0.002667
+    bin[0x14DA9..0x14DAE].copy_from_slice(&[100, 125, 150, 175, 200]);
+    bin[0x14DAE..0x14DB8]
+        .copy_from_slice(&[0xF4, 0x01, 0xE8, 0x03, 0xDC, 0x05, 0xD0, 0x07, 0xC4, 0x09]);
+    let bin = BinImage::new(bin).with_format(&format);
+
+    let resolved = tvub.resolve(&format, &bin).unwrap();
+    let y: Vec<f64> = resolved
+        .y
+        .iter()
+        .map(|v| (v * 100.0).round() / 100.0)
+        .collect();
+    assert_eq!(y, vec![7.04, 8.8, 10.56, 12.32, 14.08]);
+    let z = [1.3335, 2.667, 4.0005, 5.334, 6.6675];
+    assert_eq!(resolved.rows(), z.len());
+    for (row, expected) in resolved.values.iter().zip(z) {
+        assert!((row[0] - expected).abs() < 1e-9);
+    }
+    assert_eq!(resolved.x, vec![0.0]);
+
+    let mut dangling = tvub.clone();
+    dangling.axis[1].embedinfo.as_mut().unwrap().linkobjid = Some(0x1);
+    assert_eq!(
+        dangling.resolve(&format, &bin),
+        Err(ResolveError::UnknownLink(0x1))
+    );
+}
+
+#[test]
+fn linked_axis_math_is_not_applied_twice() {
+    let mut linked_axis = axis("z", data(0x0, Some(1), Some(3)), "X/2");
+    linked_axis.count = Some(3);
+    let breakpoints = XDFTable {
+        uid: Some(0x20),
+        axis: vec![linked_axis],
+        ..Default::default()
+    };
+    // Copy of the linked table's equation, as TunerPro writes it
+    let mut x = axis("x", None, "X/2");
+    x.embedinfo = Some(EmbedInfo {
+        etype: Some(3),
+        linkobjid: Some(0x20),
+    });
+    let table = XDFTable {
+        uid: Some(0x21),
+        axis: vec![x, axis("z", data(0x3, Some(1), Some(3)), "X")],
+        ..Default::default()
+    };
+    let format = XDFFormat {
+        tables: vec![breakpoints, table.clone()],
+        ..Default::default()
+    };
+    let bin = BinImage::new(vec![2, 4, 6, 7, 8, 9]);
+
+    let resolved = table.resolve(&format, &bin).unwrap();
+    assert_eq!(resolved.x, vec![1.0, 2.0, 3.0]);
+    assert_eq!(resolved.values, vec![vec![7.0, 8.0, 9.0]]);
+}
+
+#[test]
+fn every_sample_table_resolves() {
+    let format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let data = (0..0x40000).map(|i| (i % 251) as u8 + 1).collect();
+    let bin = BinImage::new(data).with_format(&format);
+    let mut resolved = 0;
+    let mut linked = 0;
+    for table in &format.tables {
+        if table.axis_by_id("z").is_none() {
+            continue;
+        }
+        let values = table.resolve(&format, &bin).unwrap();
+        assert_eq!(values.x.len(), values.cols());
+        assert_eq!(values.y.len(), values.rows());
+        assert!(values.values.iter().all(|row| row.len() == values.cols()));
+        resolved += 1;
+
+        for (id, breakpoints) in [("x", &values.x), ("y", &values.y)] {
+            let target = match table.axis_by_id(id).and_then(|a| a.embedinfo.as_ref()) {
+                Some(EmbedInfo {
+                    etype: Some(3),
+                    linkobjid: Some(target),
+                }) => *target,
+                _ => continue,
+            };
+            let target = format
+                .tables
+                .iter()
+                .find(|t| t.uid == Some(target))
+                .unwrap();
+            let z: Vec<f64> = target.resolve(&format, &bin).unwrap().values.concat();
+            assert_eq!(
+                breakpoints[..],
+                z[..breakpoints.len()],
+                "{id} of {:?}",
+                table.title
+            );
+            linked += 1;
+        }
+    }
+    assert!(resolved > 30);
+    assert!(linked > 0);
+}
@@ -0,0 +1,134 @@
+use xdftuneparser::data_types::*;
+
+const AMB_XDF: &str = "tests/8E0909518AK_368072_NEF_STG_1v7.xdf";
+
+fn write(format: &XDFFormat) -> Vec<u8> {
+    let mut out = Vec::new();
+    format.write_to(&mut out).unwrap();
+    out
+}
+
+/// Lines of the bundled XDF from the start tag of an element up to and including its end tag.
+fn source_block(start: &str, end: &str) -> String {
+    let source = std::fs::read_to_string(AMB_XDF).unwrap();
+    let from = source.find(start).unwrap();
+    let to = from + source[from..].find(end).unwrap() + end.len();
+    source[from..to].to_string()
+}
+
+#[test]
+fn written_items_match_tunerpro_layout() {
+    let format = XDFFormat::from_path(AMB_XDF).unwrap();
+    let written = String::from_utf8(write(&format)).unwrap();
+
+    assert!(written.starts_with("<!-- Written "));
+    for block in [
+        source_block("  <XDFHEADER>", "</XDFHEADER>"),
+        source_block("  <XDFTABLE uniqueid=\"0x1055\"", "</XDFTABLE>"),
+        source_block("  <XDFTABLE uniqueid=\"0x14DAE\"", "</XDFTABLE>"),
+        source_block("  <XDFCONSTANT uniqueid=\"0x3BFE\"", "</XDFCONSTANT>"),
+    ] {
+        assert!(written.contains(&block), "missing:\n{block}");
+    }
+}
+
+#[test]
+fn write_patch_flag_and_checksum() {
+    let format = XDFFormat {
+        version: Some("1.60".into()),
+        patches: vec![XDFPatch {
+            title: Some("Disable rear O2 sensors".into()),
+            uid: Some(0x2A1),
+            entries: vec![XDFPatchEntry {
+                name: Some("Jump".into()),
+                address: Some(0x1A2B4),
+                datasize: Some(2),
+                patchdata: Some(vec![0x0D, 0xFB]),
+                basedata: Some(vec![0x3D, 0xF7]),
+            }],
+            ..Default::default()
+        }],
+        flags: vec![XDFFlag {
+            uid: Some(0x1A3C),
+            mask: Some(0x4),
+            ..Default::default()
+        }],
+        checksums: vec![XDFChecksum {
+            uid: Some(0x5A0),
+            regions: vec![ChecksumRegion {
+                datastart: Some(0x10000),
+                storeaddress: Some(0x1FFFC),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let written = String::from_utf8(write(&format)).unwrap();
+    let body = written.split_once("\r\n").unwrap().1;
+
+    assert_eq!(
+        body,
+        "<XDFFORMAT version=\"1.60\">\r
+  <XDFPATCH uniqueid=\"0x2A1\">\r
+    <title>Disable rear O2 sensors</title>\r
+    <XDFPATCHENTRY name=\"Jump\" address=\"0x1A2B4\" datasize=\"0x2\" patchdata=\"0DFB\" basedata=\"3DF7\" />\r
+  </XDFPATCH>\r
+  <XDFFLAG uniqueid=\"0x1A3C\">\r
+    <mask>0x4</mask>\r
+  </XDFFLAG>\r
+  <XDFCHECKSUM uniqueid=\"0x5A0\">\r
+    <REGION>\r
+      <datastart>0x10000</datastart>\r
+      <storeaddress>0x1FFFC</storeaddress>\r
+    </REGION>\r
+  </XDFCHECKSUM>\r
+</XDFFORMAT>\r
+"
+    );
+    assert_eq!(XDFFormat::parse(written.as_bytes()).unwrap(), format);
+}
+
+#[test]
+fn text_is_escaped() {
+    let format = XDFFormat {
+        constants: vec![XDFConstant {
+            title: Some("A < B & \"C\"".into()),
+            description: Some("Zul\u{e4}ssiges Moment\r\nMax. 5 \u{3a9}".into()),
+            math: Some(Math {
+                vars: vec!["X".into()],
+                expression: Some("X > 0.5 ? 1 : 0".into()),
+            }),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let written = String::from_utf8(write(&format)).unwrap();
+
+    assert!(written.contains("<title>A &lt; B &amp; \"C\"</title>"));
+    assert!(written
+        .contains("<description>Zul&#228;ssiges Moment&#013;&#010;Max. 5 &#937;</description>"));
+    assert!(written.contains("<MATH equation=\"X &gt; 0.5 ? 1 : 0\">"));
+    assert_eq!(XDFFormat::parse(written.as_bytes()).unwrap(), format);
+}
+
+#[test]
+fn floats_keep_their_precision() {
+    let axis = |min| XDFAxis {
+        id: Some("z".into()),
+        min: Some(min),
+        ..Default::default()
+    };
+    let format = XDFFormat {
+        tables: vec![XDFTable {
+            axis: vec![axis(255.0), axis(0.000_012_5)],
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let written = String::from_utf8(write(&format)).unwrap();
+
+    assert!(written.contains("<min>255.000000</min>"));
+    assert!(written.contains("<min>0.0000125</min>"));
+    assert_eq!(XDFFormat::parse(written.as_bytes()).unwrap(), format);
+}